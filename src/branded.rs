@@ -0,0 +1,175 @@
+use crate::{Arena, ArenaId, Key};
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+/// An invariant lifetime that ties a [`BrandedId`] to the [`Scope`] that created it.
+type Brand<'brand> = PhantomData<fn(&'brand ()) -> &'brand ()>;
+
+impl<T, K: Key> Arena<T, K> {
+    /// Calls the function with a scope over the arena, in which IDs can be checked
+    /// once and then used to access their values without any further checks.
+    ///
+    /// Each call creates a unique brand lifetime, and [`BrandedId`]s created by the
+    /// scope can only be used with that scope. The scope does not allow inserting or
+    /// removing values, so a branded ID stays valid until the function returns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(1);
+    /// let b = arena.insert(2);
+    ///
+    /// arena.scope(|mut scope| {
+    ///     let a = scope.brand(a).unwrap();
+    ///     let b = scope.brand(b).unwrap();
+    ///
+    ///     for _ in 0..10 {
+    ///         scope[a] += scope[b];
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(arena[a], 21);
+    /// ```
+    ///
+    /// Branded IDs cannot escape the scope that created them:
+    ///
+    /// ```compile_fail
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(1);
+    ///
+    /// let branded = arena.scope(|scope| scope.brand(a).unwrap());
+    /// ```
+    ///
+    /// Nor can they be used with a different scope:
+    ///
+    /// ```compile_fail
+    /// # use arena::Arena;
+    /// let mut first = Arena::new();
+    /// let mut second = Arena::new();
+    /// let a = first.insert(1);
+    /// second.insert(2);
+    ///
+    /// first.scope(|first| {
+    ///     let a = first.brand(a).unwrap();
+    ///     second.scope(|second| second[a]);
+    /// });
+    /// ```
+    pub fn scope<F, R>(&mut self, f: F) -> R
+    where
+        F: for<'brand> FnOnce(Scope<'brand, '_, T, K>) -> R,
+    {
+        f(Scope {
+            arena: self,
+            _brand: PhantomData,
+        })
+    }
+}
+
+/// A view of an arena that can access values by [`BrandedId`] without any checks.
+///
+/// This struct is created by the [`scope`](Arena::scope) method on [`Arena`]. Values
+/// can be modified in place, but not inserted, removed or moved.
+pub struct Scope<'brand, 'a, T, K = ArenaId<T>> {
+    arena: &'a mut Arena<T, K>,
+    _brand: Brand<'brand>,
+}
+
+impl<'brand, 'a, T, K: Key> Scope<'brand, 'a, T, K> {
+    /// Returns the number of values in the arena.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns `true` if the arena contains no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+
+    /// Returns a slice of all the values in the arena.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        self.arena.as_slice()
+    }
+
+    /// Checks that the ID is assigned to a value in the arena, returning a branded ID
+    /// that can access the value without further checks, or `None` if the value is
+    /// not in the arena.
+    #[inline]
+    pub fn brand(&self, id: K) -> Option<BrandedId<'brand>> {
+        let index = self.arena.index_of(id)?;
+        Some(BrandedId {
+            index,
+            _brand: PhantomData,
+        })
+    }
+
+    /// Returns the ID of the value that the branded ID refers to.
+    #[inline]
+    pub fn id(&self, id: BrandedId<'brand>) -> K {
+        let slot = self.arena.slots[id.index].value_slot;
+        K::from_raw(self.arena.slots[slot].id(slot, self.arena.tag.get()))
+    }
+
+    /// Returns a reference to the value that the branded ID refers to.
+    #[inline]
+    pub fn get(&self, id: BrandedId<'brand>) -> &T {
+        // SAFETY: `brand` got the position from a lookup, so it is in bounds by the
+        // invariant on `lookup_slots`, and no values can be removed during the scope
+        unsafe { self.arena.values.get_unchecked(id.index) }
+    }
+
+    /// Returns a mutable reference to the value that the branded ID refers to.
+    #[inline]
+    pub fn get_mut(&mut self, id: BrandedId<'brand>) -> &mut T {
+        // SAFETY: `brand` got the position from a lookup, so it is in bounds by the
+        // invariant on `lookup_slots`, and no values can be removed during the scope
+        unsafe { self.arena.values.get_unchecked_mut(id.index) }
+    }
+}
+
+impl<'brand, 'a, T, K: Key> Index<BrandedId<'brand>> for Scope<'brand, 'a, T, K> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, id: BrandedId<'brand>) -> &T {
+        self.get(id)
+    }
+}
+
+impl<'brand, 'a, T, K: Key> IndexMut<BrandedId<'brand>> for Scope<'brand, 'a, T, K> {
+    #[inline]
+    fn index_mut(&mut self, id: BrandedId<'brand>) -> &mut T {
+        self.get_mut(id)
+    }
+}
+
+/// An ID that was checked by a [`Scope`], and can access its value without any
+/// further checks.
+///
+/// This is created by the [`brand`](Scope::brand) method on [`Scope`], and can only
+/// be used with the scope that created it.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct BrandedId<'brand> {
+    index: usize,
+    _brand: Brand<'brand>,
+}
+
+impl<'brand> BrandedId<'brand> {
+    /// Returns the position of the value in the arena.
+    #[inline]
+    pub fn index(self) -> usize {
+        self.index
+    }
+}
+
+impl<'brand> Debug for BrandedId<'brand> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BrandedId").field(&self.index).finish()
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{Arena, ArenaId, Key};
+use std::ops::{Index, IndexMut, Range};
+
+/// An arena that keeps its values grouped into a fixed number of buckets, where the
+/// values of each bucket are stored contiguously.
+///
+/// Values are stored in bucket order, so the values of a bucket can be accessed as a
+/// single slice with [`bucket_slice`](Self::bucket_slice), which is useful for
+/// processing a group of values in a batch. Like [`Arena`], values are accessed with
+/// IDs that remain valid as values are inserted, removed or moved between buckets.
+///
+/// # Examples
+///
+/// ```
+/// # use arena::BucketedArena;
+/// let mut arena = BucketedArena::new(3);
+/// let a = arena.insert(2, 'A');
+/// let b = arena.insert(0, 'B');
+/// let c = arena.insert(1, 'C');
+/// let d = arena.insert(0, 'D');
+///
+/// assert_eq!(arena.bucket_slice(0), &['B', 'D']);
+/// assert_eq!(arena.bucket_slice(1), &['C']);
+/// assert_eq!(arena.bucket_slice(2), &['A']);
+///
+/// arena.set_bucket(b, 2);
+///
+/// assert_eq!(arena.bucket_slice(0), &['D']);
+/// assert_eq!(arena.bucket_slice(2), &['B', 'A']);
+/// assert_eq!(arena[b], 'B');
+/// assert_eq!(arena.bucket_of(b), Some(2));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BucketedArena<T, K = ArenaId<T>> {
+    arena: Arena<T, K>,
+    ends: Vec<usize>,
+}
+
+impl<T> BucketedArena<T> {
+    /// Constructs a new, empty `BucketedArena<T>` with the specified number of buckets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket_count` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(unused_mut)]
+    /// # use arena::BucketedArena;
+    /// let mut arena: BucketedArena<String> = BucketedArena::new(4);
+    /// assert_eq!(arena.bucket_count(), 4);
+    /// ```
+    #[inline]
+    pub fn new(bucket_count: usize) -> Self {
+        Self::with_key(bucket_count)
+    }
+}
+
+impl<T, K: Key> BucketedArena<T, K> {
+    /// Constructs a new, empty `BucketedArena<T, K>` with the specified number of
+    /// buckets, that uses a custom key type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket_count` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(unused_mut)]
+    /// # use arena::{new_key_type, BucketedArena};
+    /// new_key_type! {
+    ///     struct MeshId;
+    /// }
+    ///
+    /// let mut arena: BucketedArena<String, MeshId> = BucketedArena::with_key(4);
+    /// ```
+    pub fn with_key(bucket_count: usize) -> Self {
+        assert!(bucket_count > 0, "arena must have at least one bucket");
+        Self {
+            arena: Arena::with_key(),
+            ends: vec![0; bucket_count],
+        }
+    }
+
+    /// Returns the number of buckets in the arena.
+    #[inline]
+    pub fn bucket_count(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Returns the number of values in the arena, across all buckets.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.arena.len()
+    }
+
+    /// Returns `true` if the arena contains no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.arena.is_empty()
+    }
+
+    /// Returns the underlying arena, which stores the values in bucket order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::BucketedArena;
+    /// let mut arena = BucketedArena::new(2);
+    /// let a = arena.insert(1, 'A');
+    /// let b = arena.insert(0, 'B');
+    ///
+    /// assert_eq!(arena.as_arena().as_slice(), &['B', 'A']);
+    /// assert_eq!(arena.as_arena().index_of(a), Some(1));
+    /// ```
+    #[inline]
+    pub fn as_arena(&self) -> &Arena<T, K> {
+        &self.arena
+    }
+
+    /// Extracts a slice containing all the arena's values, in bucket order.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        self.arena.as_slice()
+    }
+
+    /// Returns the range of positions of the bucket's values in the arena.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket` is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::BucketedArena;
+    /// let mut arena = BucketedArena::new(3);
+    /// arena.insert(0, 'A');
+    /// arena.insert(2, 'B');
+    /// arena.insert(2, 'C');
+    ///
+    /// assert_eq!(arena.bucket_range(0), 0..1);
+    /// assert_eq!(arena.bucket_range(1), 1..1);
+    /// assert_eq!(arena.bucket_range(2), 1..3);
+    /// ```
+    #[inline]
+    pub fn bucket_range(&self, bucket: usize) -> Range<usize> {
+        self.bucket_start(bucket)..self.ends[bucket]
+    }
+
+    /// Returns a slice containing the values in the bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket` is out of range.
+    #[inline]
+    pub fn bucket_slice(&self, bucket: usize) -> &[T] {
+        &self.arena.as_slice()[self.bucket_range(bucket)]
+    }
+
+    /// Returns a mutable slice containing the values in the bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket` is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::BucketedArena;
+    /// let mut arena = BucketedArena::new(2);
+    /// let a = arena.insert(0, 1);
+    /// let b = arena.insert(1, 2);
+    /// let c = arena.insert(1, 3);
+    ///
+    /// for val in arena.bucket_slice_mut(1) {
+    ///     *val *= 10;
+    /// }
+    ///
+    /// assert_eq!(arena[a], 1);
+    /// assert_eq!(arena[b], 20);
+    /// assert_eq!(arena[c], 30);
+    /// ```
+    #[inline]
+    pub fn bucket_slice_mut(&mut self, bucket: usize) -> &mut [T] {
+        let range = self.bucket_range(bucket);
+        &mut self.arena.as_mut_slice()[range]
+    }
+
+    /// Returns a reference to the value assigned with the ID.
+    #[inline]
+    pub fn get(&self, id: K) -> Option<&T> {
+        self.arena.get(id)
+    }
+
+    /// Returns a mutable reference to the value assigned with the ID.
+    #[inline]
+    pub fn get_mut(&mut self, id: K) -> Option<&mut T> {
+        self.arena.get_mut(id)
+    }
+
+    /// Returns `true` if the arena contains a value assigned with the ID.
+    #[inline]
+    pub fn contains(&self, id: K) -> bool {
+        self.arena.contains(id)
+    }
+
+    /// Returns the bucket of the value assigned with the ID, or `None` if the value
+    /// is not in the arena.
+    #[inline]
+    pub fn bucket_of(&self, id: K) -> Option<usize> {
+        let index = self.arena.index_of(id)?;
+        Some(self.ends.partition_point(|&end| end <= index))
+    }
+
+    /// Inserts a value in the bucket, returning an ID that can be used to access the
+    /// value at a later time.
+    ///
+    /// The value is added to the end of the bucket. This takes one swap for each
+    /// bucket after it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket` is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::BucketedArena;
+    /// let mut arena = BucketedArena::new(2);
+    /// let a = arena.insert(1, 'A');
+    /// let b = arena.insert(0, 'B');
+    ///
+    /// assert_eq!(arena.as_slice(), &['B', 'A']);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[b], 'B');
+    /// ```
+    pub fn insert(&mut self, bucket: usize, value: T) -> K {
+        self.check_bucket(bucket);
+        let id = self.arena.insert(value);
+
+        // move the value down to the end of its bucket, by swapping it with the first
+        // value of each bucket after it
+        let mut index = self.arena.len() - 1;
+        for b in (bucket + 1..self.ends.len()).rev() {
+            let start = self.ends[b - 1];
+            self.arena.swap(index, start);
+            index = start;
+        }
+        for end in &mut self.ends[bucket..] {
+            *end += 1;
+        }
+        id
+    }
+
+    /// Removes the value from the arena assigned to the ID. If the value existed
+    /// in the arena, it will be returned.
+    ///
+    /// The last value of the bucket is moved into the removed value's position. This
+    /// takes one swap for each bucket after it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::BucketedArena;
+    /// let mut arena = BucketedArena::new(2);
+    /// let a = arena.insert(0, 'A');
+    /// let b = arena.insert(0, 'B');
+    /// let c = arena.insert(1, 'C');
+    ///
+    /// assert_eq!(arena.remove(a), Some('A'));
+    /// assert_eq!(arena.remove(a), None);
+    ///
+    /// assert_eq!(arena.bucket_slice(0), &['B']);
+    /// assert_eq!(arena.bucket_slice(1), &['C']);
+    /// assert_eq!(arena[c], 'C');
+    /// ```
+    pub fn remove(&mut self, id: K) -> Option<T> {
+        let mut index = self.arena.index_of(id)?;
+        let bucket = self.ends.partition_point(|&end| end <= index);
+
+        // move the value to the end of the arena, by swapping it with the last value
+        // of its bucket and of each bucket after it
+        for end in &mut self.ends[bucket..] {
+            *end -= 1;
+            self.arena.swap(index, *end);
+            index = *end;
+        }
+        self.arena.pop()
+    }
+
+    /// Moves the value assigned with the ID to another bucket, without invalidating
+    /// any IDs. Returns `false` if the value is not in the arena.
+    ///
+    /// The value is added to the front of the bucket if it is moved to a later bucket,
+    /// or the end of the bucket if it is moved to an earlier one. This takes one swap
+    /// for each bucket boundary that is crossed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bucket` is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::BucketedArena;
+    /// let mut arena = BucketedArena::new(3);
+    /// let a = arena.insert(0, 'A');
+    /// let b = arena.insert(1, 'B');
+    /// let c = arena.insert(2, 'C');
+    ///
+    /// assert!(arena.set_bucket(a, 2));
+    /// assert_eq!(arena.bucket_slice(0), &[]);
+    /// assert_eq!(arena.bucket_slice(2), &['A', 'C']);
+    ///
+    /// assert!(arena.set_bucket(c, 0));
+    /// assert_eq!(arena.bucket_slice(0), &['C']);
+    /// assert_eq!(arena.bucket_slice(1), &['B']);
+    /// assert_eq!(arena.bucket_slice(2), &['A']);
+    /// ```
+    pub fn set_bucket(&mut self, id: K, bucket: usize) -> bool {
+        self.check_bucket(bucket);
+        let mut index = match self.arena.index_of(id) {
+            Some(index) => index,
+            None => return false,
+        };
+        let current = self.ends.partition_point(|&end| end <= index);
+
+        // move the value across one boundary at a time, by swapping it with the value
+        // at the edge of the bucket it is leaving and then moving the boundary past it
+        if current < bucket {
+            for end in &mut self.ends[current..bucket] {
+                *end -= 1;
+                self.arena.swap(index, *end);
+                index = *end;
+            }
+        } else {
+            for end in self.ends[bucket..current].iter_mut().rev() {
+                self.arena.swap(index, *end);
+                index = *end;
+                *end += 1;
+            }
+        }
+        true
+    }
+
+    #[inline]
+    fn bucket_start(&self, bucket: usize) -> usize {
+        match bucket {
+            0 => 0,
+            _ => self.ends[bucket - 1],
+        }
+    }
+
+    #[inline]
+    fn check_bucket(&self, bucket: usize) {
+        assert!(
+            bucket < self.ends.len(),
+            "bucket {bucket} is out of range for arena with {} buckets",
+            self.ends.len()
+        );
+    }
+}
+
+impl<T, K: Key> Index<K> for BucketedArena<T, K> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: K) -> &Self::Output {
+        &self.arena[index]
+    }
+}
+
+impl<T, K: Key> IndexMut<K> for BucketedArena<T, K> {
+    #[inline]
+    fn index_mut(&mut self, index: K) -> &mut Self::Output {
+        &mut self.arena[index]
+    }
+}
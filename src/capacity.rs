@@ -0,0 +1,295 @@
+use crate::{Arena, Key};
+use std::collections::TryReserveError;
+
+/// How an arena grows its storage when a value is inserted and it is full.
+///
+/// This is set with the [`set_growth_policy`](Arena::set_growth_policy) method on
+/// [`Arena`], and only affects growth caused by inserting values. Explicit calls to
+/// methods such as [`reserve`](Arena::reserve) are not affected.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum GrowthPolicy {
+    /// Grow like a [`Vec`], doubling the capacity each time it runs out. This keeps
+    /// inserting fast on average, but can leave up to half of the memory unused.
+    #[default]
+    Double,
+
+    /// Grow by a fixed amount of values or slots each time the capacity runs out. This
+    /// bounds the unused memory, but makes inserting many values slower.
+    Chunk(usize),
+}
+
+impl<T, K: Key> Arena<T, K> {
+    /// Returns the total number of values the arena can hold without reallocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let arena: Arena<i32> = Arena::with_capacity(10);
+    /// assert!(arena.capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.values.capacity()
+    }
+
+    /// Returns the total number of slots the arena can hold without reallocating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let arena: Arena<i32> = Arena::with_capacity(10);
+    /// assert!(arena.slot_capacity() >= 10);
+    /// ```
+    #[inline]
+    pub fn slot_capacity(&self) -> usize {
+        self.slots.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more values to be inserted in the
+    /// arena, for both the values and the slots. Free slots are reused by inserted
+    /// values, so they count towards the reserved slots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity exceeds `isize::MAX` bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from([1]);
+    /// arena.reserve(10);
+    /// assert!(arena.capacity() >= 11);
+    /// assert!(arena.slot_capacity() >= 11);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) {
+        self.values.reserve(additional);
+        self.slots
+            .reserve(additional.saturating_sub(self.free_slot_count()));
+    }
+
+    /// Reserves the minimum capacity for at least `additional` more values to be
+    /// inserted in the arena, for both the values and the slots. Free slots are reused
+    /// by inserted values, so they count towards the reserved slots.
+    ///
+    /// Unlike [`reserve`](Self::reserve), this will not deliberately over-allocate to
+    /// avoid frequent allocations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity exceeds `isize::MAX` bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from([1]);
+    /// arena.reserve_exact(10);
+    /// assert!(arena.capacity() >= 11);
+    /// assert!(arena.slot_capacity() >= 11);
+    /// ```
+    pub fn reserve_exact(&mut self, additional: usize) {
+        self.values.reserve_exact(additional);
+        self.slots
+            .reserve_exact(additional.saturating_sub(self.free_slot_count()));
+    }
+
+    /// Tries to reserve capacity for at least `additional` more values to be inserted
+    /// in the arena, for both the values and the slots. Free slots are reused by
+    /// inserted values, so they count towards the reserved slots.
+    ///
+    /// If the capacity overflows, or the allocator reports a failure, an error is
+    /// returned and the values and IDs in the arena are left unchanged, although the
+    /// capacity of the values may have grown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<i32> = Arena::new();
+    ///
+    /// assert!(arena.try_reserve(10).is_ok());
+    /// assert!(arena.capacity() >= 10);
+    ///
+    /// assert!(arena.try_reserve(usize::MAX).is_err());
+    /// ```
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.values.try_reserve(additional)?;
+        self.slots
+            .try_reserve(additional.saturating_sub(self.free_slot_count()))
+    }
+
+    /// Tries to reserve the minimum capacity for at least `additional` more values to
+    /// be inserted in the arena, for both the values and the slots.
+    ///
+    /// If the capacity overflows, or the allocator reports a failure, an error is
+    /// returned and the values and IDs in the arena are left unchanged, although the
+    /// capacity of the values may have grown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<i32> = Arena::new();
+    ///
+    /// assert!(arena.try_reserve_exact(10).is_ok());
+    /// assert!(arena.capacity() >= 10);
+    /// ```
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        self.values.try_reserve_exact(additional)?;
+        self.slots
+            .try_reserve_exact(additional.saturating_sub(self.free_slot_count()))
+    }
+
+    /// Reserves capacity for at least `additional` more values, without reserving
+    /// any slots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity exceeds `isize::MAX` bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<i32> = Arena::new();
+    /// arena.reserve_values(10);
+    /// assert!(arena.capacity() >= 10);
+    /// assert_eq!(arena.slot_capacity(), 0);
+    /// ```
+    #[inline]
+    pub fn reserve_values(&mut self, additional: usize) {
+        self.values.reserve(additional);
+    }
+
+    /// Reserves capacity for at least `additional` more slots, on top of the slots
+    /// the arena already has, without reserving any values.
+    ///
+    /// Unlike [`reserve`](Self::reserve), free slots do not count towards the reserved
+    /// slots, so this makes room for `additional` new slots even if the inserted values
+    /// could reuse free ones. Use `reserve` to avoid allocating more slots than needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity exceeds `isize::MAX` bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<i32> = Arena::new();
+    /// arena.reserve_slots(10);
+    /// assert!(arena.slot_capacity() >= 10);
+    /// assert_eq!(arena.capacity(), 0);
+    /// ```
+    #[inline]
+    pub fn reserve_slots(&mut self, additional: usize) {
+        self.slots.reserve(additional);
+    }
+
+    /// Shrinks the capacity of the values and the slots as much as possible.
+    ///
+    /// This does not remove any free slots, see [`trim_free_tail`](Self::trim_free_tail)
+    /// and [`compact_slots`](Self::compact_slots) for that.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::with_capacity(10);
+    /// arena.extend([1, 2, 3]);
+    ///
+    /// arena.shrink_to_fit();
+    /// assert!(arena.capacity() >= 3);
+    /// assert!(arena.slot_capacity() >= 3);
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        self.values.shrink_to_fit();
+        self.slots.shrink_to_fit();
+    }
+
+    /// Shrinks the capacity of the values and the slots with a lower bound.
+    ///
+    /// The capacities will remain at least as large as both the lower bound and their
+    /// lengths. If a capacity is already less than the lower bound, it is not changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::with_capacity(10);
+    /// arena.extend([1, 2, 3]);
+    ///
+    /// arena.shrink_to(4);
+    /// assert!(arena.capacity() >= 4);
+    /// assert!(arena.slot_capacity() >= 4);
+    /// ```
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        self.values.shrink_to(min_capacity);
+        self.slots.shrink_to(min_capacity);
+    }
+
+    /// Returns the policy the arena uses to grow when inserting values.
+    #[inline]
+    pub fn growth_policy(&self) -> GrowthPolicy {
+        self.growth
+    }
+
+    /// Sets the policy the arena uses to grow when inserting values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the policy is [`GrowthPolicy::Chunk`] with a size of zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, GrowthPolicy};
+    /// let mut arena = Arena::new();
+    /// arena.set_growth_policy(GrowthPolicy::Chunk(64));
+    ///
+    /// for i in 0..100 {
+    ///     arena.insert(i);
+    /// }
+    /// assert_eq!(arena.capacity(), 128);
+    /// ```
+    pub fn set_growth_policy(&mut self, policy: GrowthPolicy) {
+        if let GrowthPolicy::Chunk(chunk) = policy {
+            assert!(chunk > 0, "growth chunk size must be greater than zero");
+        }
+        self.growth = policy;
+    }
+
+    /// Grows the values and slots according to the growth policy if inserting a value
+    /// would need to reallocate them.
+    pub(crate) fn grow_for_insert(&mut self) {
+        if let GrowthPolicy::Chunk(chunk) = self.growth {
+            if self.values.len() == self.values.capacity() {
+                self.values.reserve_exact(chunk);
+            }
+            if self.first_free.is_none() && self.slots.len() == self.slots.capacity() {
+                self.slots.reserve_exact(chunk);
+            }
+        }
+    }
+
+    /// Tries to grow the values and slots according to the growth policy, so that
+    /// inserting a value does not need to reallocate them.
+    pub(crate) fn try_grow_for_insert(&mut self) -> Result<(), TryReserveError> {
+        if self.values.len() == self.values.capacity() {
+            match self.growth {
+                GrowthPolicy::Double => self.values.try_reserve(1)?,
+                GrowthPolicy::Chunk(chunk) => self.values.try_reserve_exact(chunk)?,
+            }
+        }
+        if self.first_free.is_none() && self.slots.len() == self.slots.capacity() {
+            match self.growth {
+                GrowthPolicy::Double => self.slots.try_reserve(1)?,
+                GrowthPolicy::Chunk(chunk) => self.slots.try_reserve_exact(chunk)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{lookup_slots, Arena, ArenaError, ArenaId, Key, Slot, Tag};
+use std::cell::{Cell, UnsafeCell};
+use std::fmt::{self, Debug, Formatter};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+/// The borrow flag of a value that is mutably borrowed. Positive flags count the
+/// shared borrows of a value.
+const WRITING: isize = -1;
+
+impl<T, K: Key> Arena<T, K> {
+    /// Returns a view of the arena that can borrow values through a shared reference,
+    /// checking at runtime that no value is borrowed mutably more than once.
+    ///
+    /// The arena stays mutably borrowed while the view is alive, so values cannot be
+    /// inserted, removed or moved until it is dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaError};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(1);
+    /// let b = arena.insert(2);
+    ///
+    /// let cell = arena.as_cell();
+    /// let mut a_val = cell.borrow_mut(a).unwrap();
+    /// let b_val = cell.borrow(b).unwrap();
+    /// *a_val += *b_val;
+    ///
+    /// assert_eq!(cell.borrow(a).unwrap_err(), ArenaError::BorrowConflict(a.into()));
+    /// drop(a_val);
+    /// assert_eq!(*cell.borrow(a).unwrap(), 3);
+    /// ```
+    ///
+    /// The arena cannot be changed while a value is borrowed:
+    ///
+    /// ```compile_fail
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(1);
+    ///
+    /// let cell = arena.as_cell();
+    /// let a_val = cell.borrow(a).unwrap();
+    /// arena.remove(a);
+    /// println!("{}", *a_val);
+    /// ```
+    pub fn as_cell(&mut self) -> ArenaCell<'_, T, K> {
+        let tag = self.tag.get();
+        let values = self.values.as_mut_slice() as *mut [T] as *const [UnsafeCell<T>];
+        ArenaCell {
+            // SAFETY: `UnsafeCell<T>` has the same memory layout as `T`, and the
+            // values are borrowed mutably for the lifetime of the view
+            values: unsafe { &*values },
+            flags: (0..self.values.len()).map(|_| Cell::new(0)).collect(),
+            slots: &self.slots,
+            tag,
+            _key: PhantomData,
+        }
+    }
+}
+
+/// A view of an arena that checks borrows of its values at runtime.
+///
+/// This struct is created by the [`as_cell`](Arena::as_cell) method on [`Arena`].
+/// Each value has its own borrow flag, like a [`RefCell`](std::cell::RefCell), so
+/// any amount of values can be borrowed mutably at the same time, as long as each
+/// of them is only borrowed once.
+pub struct ArenaCell<'a, T, K = ArenaId<T>> {
+    values: &'a [UnsafeCell<T>],
+    flags: Box<[Cell<isize>]>,
+    slots: &'a [Slot],
+    tag: Tag,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<'a, T, K: Key> ArenaCell<'a, T, K> {
+    /// Returns the number of values in the arena.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if the arena contains no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns `true` if the arena contains a value assigned with the ID.
+    #[inline]
+    pub fn contains(&self, id: K) -> bool {
+        lookup_slots(self.slots, self.tag, id.into_raw()).is_ok()
+    }
+
+    /// Immutably borrows the value assigned with the ID, or returns an error if the
+    /// value is not in the arena or is currently borrowed mutably.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaError};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    ///
+    /// let cell = arena.as_cell();
+    /// let first = cell.borrow(a).unwrap();
+    /// let second = cell.borrow(a).unwrap();
+    ///
+    /// assert_eq!(*first, *second);
+    /// assert_eq!(cell.borrow_mut(a).unwrap_err(), ArenaError::BorrowConflict(a.into()));
+    /// ```
+    pub fn borrow(&self, id: K) -> Result<CellRef<'_, T>, ArenaError> {
+        let raw = id.into_raw();
+        let index = lookup_slots(self.slots, self.tag, raw)?;
+        let flag = &self.flags[index];
+        match flag.get() {
+            WRITING => return Err(ArenaError::BorrowConflict(raw)),
+            isize::MAX => panic!("too many immutable borrows of {raw:?}"),
+            count => flag.set(count + 1),
+        }
+
+        // SAFETY: the flag shows that the value is not borrowed mutably, and it will
+        // not be until the reference is dropped
+        let value = unsafe { &*self.values[index].get() };
+        Ok(CellRef { value, flag })
+    }
+
+    /// Mutably borrows the value assigned with the ID, or returns an error if the
+    /// value is not in the arena or is currently borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaError};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    ///
+    /// let cell = arena.as_cell();
+    /// let mut a_val = cell.borrow_mut(a).unwrap();
+    /// let mut b_val = cell.borrow_mut(b).unwrap();
+    /// std::mem::swap(&mut *a_val, &mut *b_val);
+    ///
+    /// assert_eq!(cell.borrow_mut(a).unwrap_err(), ArenaError::BorrowConflict(a.into()));
+    /// drop((a_val, b_val));
+    ///
+    /// assert_eq!(arena[a], 'B');
+    /// assert_eq!(arena[b], 'A');
+    /// ```
+    pub fn borrow_mut(&self, id: K) -> Result<CellRefMut<'_, T>, ArenaError> {
+        let raw = id.into_raw();
+        let index = lookup_slots(self.slots, self.tag, raw)?;
+        let flag = &self.flags[index];
+        if flag.get() != 0 {
+            return Err(ArenaError::BorrowConflict(raw));
+        }
+        flag.set(WRITING);
+
+        // SAFETY: the flag shows that the value is not borrowed, and it will not be
+        // until the reference is dropped
+        let value = unsafe { &mut *self.values[index].get() };
+        Ok(CellRefMut { value, flag })
+    }
+}
+
+/// A borrowed reference to a value in an [`ArenaCell`].
+///
+/// This struct is created by the [`borrow`](ArenaCell::borrow) method on
+/// [`ArenaCell`].
+pub struct CellRef<'b, T> {
+    value: &'b T,
+    flag: &'b Cell<isize>,
+}
+
+impl<'b, T> Deref for CellRef<'b, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'b, T> Drop for CellRef<'b, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.flag.set(self.flag.get() - 1);
+    }
+}
+
+impl<'b, T: Debug> Debug for CellRef<'b, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// A mutably borrowed reference to a value in an [`ArenaCell`].
+///
+/// This struct is created by the [`borrow_mut`](ArenaCell::borrow_mut) method on
+/// [`ArenaCell`].
+pub struct CellRefMut<'b, T> {
+    value: &'b mut T,
+    flag: &'b Cell<isize>,
+}
+
+impl<'b, T> Deref for CellRefMut<'b, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<'b, T> DerefMut for CellRefMut<'b, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<'b, T> Drop for CellRefMut<'b, T> {
+    #[inline]
+    fn drop(&mut self) {
+        self.flag.set(0);
+    }
+}
+
+impl<'b, T: Debug> Debug for CellRefMut<'b, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
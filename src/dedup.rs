@@ -0,0 +1,205 @@
+use crate::{Arena, IdRemap, Key};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+impl<T, K: Key> Arena<T, K> {
+    /// Removes all but the first of consecutive values that the function considers
+    /// equal, returning a remap from the IDs of the removed values to the ID of the
+    /// value that was kept in their place.
+    ///
+    /// The function is passed a value and the last value that was kept before it, in
+    /// that order, and the value is removed if the function returns `true`. The
+    /// remaining values keep their order and their IDs.
+    ///
+    /// If the function panics, the arena is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert("foo");
+    /// let b = arena.insert("FOO");
+    /// let c = arena.insert("bar");
+    ///
+    /// let remap = arena.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    ///
+    /// assert_eq!(arena.as_slice(), &["foo", "bar"]);
+    /// assert_eq!(remap.get(b), Some(a));
+    /// assert_eq!(arena[c], "bar");
+    /// ```
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F) -> IdRemap<K>
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        let mut kept = Vec::with_capacity(self.values.len());
+        let mut last = 0;
+        for i in 0..self.values.len() {
+            if i > 0 {
+                let (head, tail) = self.values.split_at_mut(i);
+                if same_bucket(&mut tail[0], &mut head[last]) {
+                    kept.push(Some(last));
+                    continue;
+                }
+            }
+            kept.push(None);
+            last = i;
+        }
+        self.remove_duplicates(kept)
+    }
+
+    /// Removes all but the first of consecutive values that resolve to the same key,
+    /// returning a remap from the IDs of the removed values to the ID of the value
+    /// that was kept in their place.
+    ///
+    /// If the key function panics, the arena is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(10);
+    /// let b = arena.insert(11);
+    /// let c = arena.insert(20);
+    /// let d = arena.insert(10);
+    ///
+    /// let remap = arena.dedup_by_key(|val| *val / 10);
+    ///
+    /// assert_eq!(arena.as_slice(), &[10, 20, 10]);
+    /// assert_eq!(remap.get(b), Some(a));
+    /// assert_eq!(remap.get(d), None);
+    /// ```
+    #[inline]
+    pub fn dedup_by_key<F, U>(&mut self, mut key: F) -> IdRemap<K>
+    where
+        F: FnMut(&mut T) -> U,
+        U: PartialEq,
+    {
+        self.dedup_by(|a, b| key(a) == key(b))
+    }
+
+    /// Removes all but the first of consecutive equal values, returning a remap from
+    /// the IDs of the removed values to the ID of the value that was kept in their
+    /// place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('A');
+    /// let c = arena.insert('B');
+    /// let d = arena.insert('A');
+    ///
+    /// let remap = arena.dedup();
+    ///
+    /// assert_eq!(arena.as_slice(), &['A', 'B', 'A']);
+    /// assert_eq!(remap.len(), 1);
+    /// assert_eq!(remap.get(b), Some(a));
+    /// ```
+    #[inline]
+    pub fn dedup(&mut self) -> IdRemap<K>
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b)
+    }
+
+    /// Removes all but the first of each group of equal values, wherever they are in
+    /// the arena, returning a remap from the IDs of the removed values to the ID of
+    /// the value that was kept in their place.
+    ///
+    /// Unlike [`sort_dedup`](Self::sort_dedup), this does not need the values to be
+    /// sorted, and the remaining values keep their order and their IDs. It takes
+    /// *O*(*n*) time on average, but needs to hash each value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('A');
+    /// let d = arena.insert('B');
+    ///
+    /// let remap = arena.dedup_by_hash();
+    ///
+    /// assert_eq!(arena.as_slice(), &['A', 'B']);
+    /// assert_eq!(remap.get(c), Some(a));
+    /// assert_eq!(remap.get(d), Some(b));
+    /// ```
+    pub fn dedup_by_hash(&mut self) -> IdRemap<K>
+    where
+        T: Hash + Eq,
+    {
+        let mut first = HashMap::with_capacity(self.values.len());
+        let kept = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(i, val)| match first.get(val) {
+                Some(&index) => Some(index),
+                None => {
+                    first.insert(val, i);
+                    None
+                }
+            })
+            .collect();
+        self.remove_duplicates(kept)
+    }
+
+    /// Removes the values marked as duplicates, where `kept[i]` is the position of the
+    /// value that replaces the value at position `i`, or `None` if it is kept.
+    fn remove_duplicates(&mut self, kept: Vec<Option<usize>>) -> IdRemap<K> {
+        // resolve the IDs of the kept values before the positions change
+        let kept: Vec<Option<K>> = kept
+            .into_iter()
+            .map(|index| index.and_then(|index| self.id_at(index)))
+            .collect();
+
+        let mut remap = IdRemap::new();
+        let mut kept = kept.into_iter();
+        self.retain(|id, _| match kept.next().flatten() {
+            Some(to) => {
+                remap.insert(id, to);
+                false
+            }
+            None => true,
+        });
+        remap
+    }
+}
+
+impl<T: Ord, K: Key> Arena<T, K> {
+    /// Sorts the values in the arena and removes all but the first of each group of
+    /// equal values, returning a remap from the IDs of the removed values to the ID
+    /// of the value that was kept in their place.
+    ///
+    /// The sort is stable, so the value that is kept is the one that came first in
+    /// the arena. The remaining values keep their IDs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(3);
+    /// let b = arena.insert(1);
+    /// let c = arena.insert(3);
+    /// let d = arena.insert(2);
+    ///
+    /// let remap = arena.sort_dedup();
+    ///
+    /// assert_eq!(arena.as_slice(), &[1, 2, 3]);
+    /// assert_eq!(remap.get(c), Some(a));
+    /// assert_eq!(arena[b], 1);
+    /// assert_eq!(arena[d], 2);
+    /// ```
+    pub fn sort_dedup(&mut self) -> IdRemap<K> {
+        self.sort();
+        self.dedup()
+    }
+}
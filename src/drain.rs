@@ -0,0 +1,346 @@
+use crate::{check_range, Arena, ArenaId, Key, Slot, State, Tag};
+use std::iter::FusedIterator;
+use std::mem;
+use std::ops::{Range, RangeBounds};
+use std::ptr;
+
+impl<T, K: Key> Arena<T, K> {
+    /// Removes all values from the arena, returning them along with their IDs
+    /// in an iterator.
+    ///
+    /// The arena keeps all of its slots, which will be reused for any values added
+    /// after this call. If the iterator is dropped before being fully consumed, the
+    /// remaining values are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    ///
+    /// let drained: Vec<_> = arena.drain().collect();
+    /// assert_eq!(drained, vec![(a, 'A'), (b, 'B')]);
+    ///
+    /// assert!(arena.is_empty());
+    /// assert_eq!(arena.slot_count(), 2);
+    /// assert_eq!(arena.get(a), None);
+    /// ```
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, T, K> {
+        self.drain_range(..)
+    }
+
+    /// Removes the values in the range of positions from the arena, returning them
+    /// along with their IDs in an iterator.
+    ///
+    /// The values after the range are shifted down to fill the gap, preserving their
+    /// order and IDs. If the iterator is dropped before being fully consumed, the
+    /// remaining values in the range are dropped.
+    ///
+    /// If the iterator is leaked instead of dropped (with [`mem::forget`], for
+    /// example), the arena loses the values from the start of the range onward, and
+    /// their IDs never match a value again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point is greater than the end point or if the end point
+    /// is greater than the length of the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    /// let d = arena.insert('D');
+    ///
+    /// let drained: Vec<_> = arena.drain_range(1..3).collect();
+    /// assert_eq!(drained, vec![(b, 'B'), (c, 'C')]);
+    ///
+    /// assert_eq!(arena.as_slice(), &['A', 'D']);
+    /// assert_eq!(arena.get(a), Some(&'A'));
+    /// assert_eq!(arena.get(b), None);
+    /// assert_eq!(arena.get(d), Some(&'D'));
+    /// ```
+    pub fn drain_range<R>(&mut self, range: R) -> Drain<'_, T, K>
+    where
+        R: RangeBounds<usize>,
+    {
+        let len = self.values.len();
+        let Range { start, end } = check_range(range, len);
+
+        // collect the IDs before their slots get freed
+        let tag = self.tag.get();
+        let ids: Vec<K> = (start..end)
+            .map(|i| {
+                let slot = self.slots[i].value_slot;
+                K::from_raw(self.slots[slot].id(slot, tag))
+            })
+            .collect();
+        for i in start..end {
+            self.free_slot(self.slots[i].value_slot);
+        }
+
+        // shift the back-references of the values after the range down, and retire
+        // their slots until the iterator is dropped, so that if it gets leaked along
+        // with those values, their IDs never match again
+        let count = end - start;
+        for i in end..len {
+            let slot = self.slots[i].value_slot;
+            self.slots[i - count].value_slot = slot;
+            self.slots[slot].state = State::Retired;
+        }
+        self.retired += len - end;
+
+        let partition = match self.partition {
+            p if p > start => start + p.saturating_sub(end),
+            p => p,
+        };
+        self.partition = self.partition.min(start);
+
+        let Arena {
+            values,
+            slots,
+            retired,
+            partition: arena_partition,
+            ..
+        } = self;
+        Drain {
+            ids: ids.into_iter(),
+            values: values.drain(start..end),
+            slots,
+            retired,
+            tail: start..start + (len - end),
+            partition: arena_partition,
+            new_partition: partition,
+        }
+    }
+
+    /// Returns an iterator that removes the values for which the predicate returns
+    /// `true`, yielding them along with their IDs.
+    ///
+    /// Values are only tested and removed as the iterator is advanced. If the iterator
+    /// is dropped early, the values that have not been tested are kept. The values
+    /// that remain in the arena keep their order and IDs.
+    ///
+    /// If the iterator is leaked instead of dropped (with [`mem::forget`], for
+    /// example), the arena loses all of its values, and their IDs never match a value
+    /// again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from([1, 2, 3, 4, 5, 6]);
+    ///
+    /// let evens: Vec<i32> = arena
+    ///     .extract_if(|_, val| *val % 2 == 0)
+    ///     .map(|(_, val)| val)
+    ///     .collect();
+    ///
+    /// assert_eq!(evens, vec![2, 4, 6]);
+    /// assert_eq!(arena.as_slice(), &[1, 3, 5]);
+    /// ```
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, T, K, F>
+    where
+        F: FnMut(K, &mut T) -> bool,
+    {
+        let len = self.values.len();
+        let tag = self.tag.get();
+        let partition = mem::take(&mut self.partition);
+
+        // the values are moved around manually until the iterator is dropped, so if
+        // it gets leaked, the arena loses its values instead of exposing them. Their
+        // slots are retired until then, so that their IDs never match again
+        for i in 0..len {
+            let slot = self.slots[i].value_slot;
+            self.slots[slot].state = State::Retired;
+        }
+        self.retired += len;
+        unsafe {
+            self.values.set_len(0);
+        }
+
+        ExtractIf {
+            arena: self,
+            pred,
+            read: 0,
+            kept: 0,
+            len,
+            partition,
+            split: 0,
+            tag,
+        }
+    }
+}
+
+/// A draining iterator over an arena's ID/value pairs.
+///
+/// This struct is created by the [`drain`](Arena::drain) and
+/// [`drain_range`](Arena::drain_range) methods on [`Arena`].
+pub struct Drain<'a, T, K = ArenaId<T>> {
+    ids: std::vec::IntoIter<K>,
+    values: std::vec::Drain<'a, T>,
+    slots: &'a mut Vec<Slot>,
+    retired: &'a mut usize,
+    tail: Range<usize>,
+    partition: &'a mut usize,
+    new_partition: usize,
+}
+
+impl<'a, T, K> Iterator for Drain<'a, T, K> {
+    type Item = (K, T);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.ids.next()?, self.values.next()?))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<'a, T, K> DoubleEndedIterator for Drain<'a, T, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.ids.next_back()?, self.values.next_back()?))
+    }
+}
+
+impl<'a, T, K> ExactSizeIterator for Drain<'a, T, K> {}
+
+impl<'a, T, K> FusedIterator for Drain<'a, T, K> {}
+
+impl<'a, T, K> Drop for Drain<'a, T, K> {
+    fn drop(&mut self) {
+        // the values after the range are moved down once `values` is dropped, so
+        // their slots can be used again
+        for i in self.tail.clone() {
+            let slot = self.slots[i].value_slot;
+            self.slots[slot].state = State::Used { value: i };
+        }
+        *self.retired -= self.tail.len();
+        *self.partition = self.new_partition;
+    }
+}
+
+/// An iterator which removes the ID/value pairs of an arena that match a predicate.
+///
+/// This struct is created by the [`extract_if`](Arena::extract_if) method on [`Arena`].
+pub struct ExtractIf<'a, T, K, F>
+where
+    K: Key,
+    F: FnMut(K, &mut T) -> bool,
+{
+    arena: &'a mut Arena<T, K>,
+    pred: F,
+    read: usize,
+    kept: usize,
+    len: usize,
+    partition: usize,
+    split: usize,
+    tag: Tag,
+}
+
+impl<'a, T, K, F> Iterator for ExtractIf<'a, T, K, F>
+where
+    K: Key,
+    F: FnMut(K, &mut T) -> bool,
+{
+    type Item = (K, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ptr = self.arena.values.as_mut_ptr();
+        while self.read < self.len {
+            let i = self.read;
+            let slot = self.arena.slots[i].value_slot;
+            let id = K::from_raw(self.arena.slots[slot].id(slot, self.tag));
+
+            // SAFETY: values in `read..len` have not been moved yet
+            let extract = (self.pred)(id, unsafe { &mut *ptr.add(i) });
+
+            // only advance after the predicate, so that if it panics, the value is kept
+            self.read += 1;
+
+            if extract {
+                self.arena.retired -= 1;
+                self.arena.free_slot(slot);
+
+                // SAFETY: the value is never read again, its position is either
+                // overwritten or truncated once the iterator is dropped
+                return Some((id, unsafe { ptr::read(ptr.add(i)) }));
+            }
+
+            if i < self.partition {
+                self.split += 1;
+            }
+
+            // move the kept value down to fill the gap left by the extracted ones
+            if self.kept < i {
+                // SAFETY: the value at `kept` has already been moved or extracted
+                unsafe {
+                    ptr::copy_nonoverlapping(ptr.add(i), ptr.add(self.kept), 1);
+                }
+                self.arena.slots[self.kept].value_slot = slot;
+            }
+            self.kept += 1;
+        }
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.len - self.read))
+    }
+}
+
+impl<'a, T, K, F> Drop for ExtractIf<'a, T, K, F>
+where
+    K: Key,
+    F: FnMut(K, &mut T) -> bool,
+{
+    fn drop(&mut self) {
+        // keep all the values that were not tested, shifting them down
+        let tail = self.len - self.read;
+        if self.kept < self.read {
+            let ptr = self.arena.values.as_mut_ptr();
+
+            // SAFETY: the values in `read..len` are all moved to the end of the kept
+            // values, which only overwrites positions that were already moved out
+            unsafe {
+                ptr::copy(ptr.add(self.read), ptr.add(self.kept), tail);
+            }
+            for i in 0..tail {
+                let slot = self.arena.slots[self.read + i].value_slot;
+                self.arena.slots[self.kept + i].value_slot = slot;
+            }
+        }
+
+        // all the remaining values are in place, so their slots can be used again
+        let len = self.kept + tail;
+        for i in 0..len {
+            let slot = self.arena.slots[i].value_slot;
+            self.arena.slots[slot].state = State::Used { value: i };
+        }
+        self.arena.retired -= len;
+
+        // SAFETY: all values up to the new length have been initialized
+        unsafe {
+            self.arena.values.set_len(len);
+        }
+        self.arena.partition = self.split + self.partition.saturating_sub(self.read);
+    }
+}
+
+impl<'a, T, K, F> FusedIterator for ExtractIf<'a, T, K, F>
+where
+    K: Key,
+    F: FnMut(K, &mut T) -> bool,
+{
+}
@@ -0,0 +1,240 @@
+use crate::{Arena, ArenaId, Key};
+use std::fmt::{self, Display, Formatter};
+use std::mem;
+
+impl<T, K: Key> Arena<T, K> {
+    /// Gets the entry of the ID for in-place manipulation.
+    ///
+    /// The ID is only looked up once, so the returned entry can be used to access,
+    /// replace or remove the value without repeating the lookup.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, Entry, VacantReason};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(1);
+    /// let b = arena.insert(2);
+    ///
+    /// if let Entry::Occupied(mut entry) = arena.entry(a) {
+    ///     *entry.get_mut() += 10;
+    /// }
+    /// assert_eq!(arena[a], 11);
+    ///
+    /// // remove a value if it is too small
+    /// if let Entry::Occupied(entry) = arena.entry(b) {
+    ///     if *entry.get() < 5 {
+    ///         entry.remove();
+    ///     }
+    /// }
+    ///
+    /// match arena.entry(b) {
+    ///     Entry::Vacant(entry) => assert_eq!(entry.reason(), VacantReason::Removed),
+    ///     Entry::Occupied(_) => unreachable!(),
+    /// }
+    /// ```
+    pub fn entry(&mut self, id: K) -> Entry<'_, T, K> {
+        let raw = id.into_raw();
+        match self.lookup(raw) {
+            Some(index) => Entry::Occupied(OccupiedEntry {
+                arena: self,
+                id,
+                index,
+            }),
+            None => {
+                let reason = match self.slots.get(raw.slot()) {
+                    None => VacantReason::OutOfRange,
+                    Some(_) => VacantReason::Removed,
+                };
+                Entry::Vacant(VacantEntry { id, reason })
+            }
+        }
+    }
+
+    /// Replaces the value assigned with the ID, returning the old value, or `None`
+    /// if the value is not in the arena. The ID remains assigned to the new value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    ///
+    /// assert_eq!(arena.replace(a, 'B'), Some('A'));
+    /// assert_eq!(arena[a], 'B');
+    ///
+    /// arena.remove(a);
+    /// assert_eq!(arena.replace(a, 'C'), None);
+    /// ```
+    #[inline]
+    pub fn replace(&mut self, id: K, value: T) -> Option<T> {
+        self.get_mut(id).map(|val| mem::replace(val, value))
+    }
+
+    /// Takes the value assigned with the ID, leaving the default value in its place,
+    /// or returns `None` if the value is not in the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(String::from("foo"));
+    ///
+    /// assert_eq!(arena.take(a), Some(String::from("foo")));
+    /// assert_eq!(arena[a], "");
+    /// ```
+    #[inline]
+    pub fn take(&mut self, id: K) -> Option<T>
+    where
+        T: Default,
+    {
+        self.get_mut(id).map(mem::take)
+    }
+
+    /// Calls the function with a mutable reference to the value assigned with the ID,
+    /// returning its result, or `None` if the value is not in the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(1);
+    ///
+    /// assert_eq!(arena.update(a, |val| { *val += 1; *val }), Some(2));
+    ///
+    /// arena.remove(a);
+    /// assert_eq!(arena.update(a, |val| { *val += 1; *val }), None);
+    /// ```
+    #[inline]
+    pub fn update<F, R>(&mut self, id: K, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.get_mut(id).map(f)
+    }
+}
+
+/// A view into a single entry of an arena, which may either be occupied or vacant.
+///
+/// This enum is created by the [`entry`](Arena::entry) method on [`Arena`].
+#[derive(Debug)]
+pub enum Entry<'a, T, K: Key = ArenaId<T>> {
+    /// The ID is assigned to a value in the arena.
+    Occupied(OccupiedEntry<'a, T, K>),
+
+    /// The ID is not assigned to any value in the arena.
+    Vacant(VacantEntry<K>),
+}
+
+impl<'a, T, K: Key> Entry<'a, T, K> {
+    /// Returns the ID of the entry.
+    #[inline]
+    pub fn id(&self) -> K {
+        match self {
+            Entry::Occupied(entry) => entry.id(),
+            Entry::Vacant(entry) => entry.id(),
+        }
+    }
+}
+
+/// A view into an occupied entry of an arena.
+///
+/// This struct is part of the [`Entry`] enum.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, T, K: Key = ArenaId<T>> {
+    arena: &'a mut Arena<T, K>,
+    id: K,
+    index: usize,
+}
+
+impl<'a, T, K: Key> OccupiedEntry<'a, T, K> {
+    /// Returns the ID of the entry.
+    #[inline]
+    pub fn id(&self) -> K {
+        self.id
+    }
+
+    /// Returns the current position of the value in the arena's slice of values.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns a reference to the value.
+    #[inline]
+    pub fn get(&self) -> &T {
+        &self.arena.values[self.index]
+    }
+
+    /// Returns a mutable reference to the value.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.arena.values[self.index]
+    }
+
+    /// Converts the entry into a mutable reference to the value, which lives as long
+    /// as the borrow of the arena.
+    #[inline]
+    pub fn into_mut(self) -> &'a mut T {
+        &mut self.arena.values[self.index]
+    }
+
+    /// Replaces the value, returning the old one. The ID remains assigned to the new value.
+    #[inline]
+    pub fn insert(&mut self, value: T) -> T {
+        mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the value from the arena and returns it.
+    #[inline]
+    pub fn remove(self) -> T {
+        let slot = self.arena.slots[self.index].value_slot;
+        self.arena.remove_value(slot, self.index)
+    }
+}
+
+/// A view into a vacant entry of an arena.
+///
+/// This struct is part of the [`Entry`] enum.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct VacantEntry<K> {
+    id: K,
+    reason: VacantReason,
+}
+
+impl<K: Key> VacantEntry<K> {
+    /// Returns the ID of the entry.
+    #[inline]
+    pub fn id(&self) -> K {
+        self.id
+    }
+
+    /// Returns the reason the ID is not assigned to a value.
+    #[inline]
+    pub fn reason(&self) -> VacantReason {
+        self.reason
+    }
+}
+
+/// The reason an ID is not assigned to a value in an arena.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum VacantReason {
+    /// The ID refers to a slot that the arena does not have, such as
+    /// [`ArenaId::NULL`], or an ID from a larger arena.
+    OutOfRange,
+
+    /// The value assigned with the ID was removed from the arena.
+    Removed,
+}
+
+impl Display for VacantReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VacantReason::OutOfRange => f.write_str("ID refers to a slot out of range"),
+            VacantReason::Removed => f.write_str("ID refers to a removed value"),
+        }
+    }
+}
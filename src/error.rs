@@ -0,0 +1,78 @@
+use crate::RawId;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// An error returned when an arena cannot access a value.
+///
+/// This is returned by the fallible accessors of [`Arena`](crate::Arena), such as
+/// [`try_get`](crate::Arena::try_get) and [`try_remove`](crate::Arena::try_remove).
+/// IDs are stored as a [`RawId`], so the error does not depend on the key type.
+///
+/// # Examples
+///
+/// ```
+/// # use arena::{Arena, ArenaError};
+/// let mut arena = Arena::new();
+/// let a = arena.insert('A');
+/// arena.remove(a);
+///
+/// assert_eq!(arena.try_get(a), Err(ArenaError::StaleId(a.into())));
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ArenaError {
+    /// The ID refers to a slot that the arena does not have, such as
+    /// [`ArenaId::NULL`](crate::ArenaId::NULL), or an ID from a larger arena.
+    OutOfRange(RawId),
+
+    /// The value assigned with the ID was removed from the arena.
+    StaleId(RawId),
+
+    /// The ID refers to the same value as another ID in a request for multiple
+    /// mutable references.
+    AliasedIds(RawId),
+
+    /// The ID was created by a different arena. This is only detected when the
+    /// `instance-tags` feature is enabled.
+    ForeignArena(RawId),
+
+    /// The value assigned with the ID is already borrowed in a way that conflicts with
+    /// the requested borrow, through an [`ArenaCell`](crate::ArenaCell).
+    BorrowConflict(RawId),
+
+    /// A position is out of bounds of the arena's values.
+    IndexOutOfBounds {
+        /// The position that was accessed.
+        index: usize,
+        /// The number of values in the arena.
+        len: usize,
+    },
+}
+
+impl Display for ArenaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ArenaError::OutOfRange(id) => write!(f, "{id:?} refers to a slot out of range"),
+            ArenaError::StaleId(id) => write!(f, "{id:?} refers to a removed value"),
+            ArenaError::AliasedIds(id) => {
+                write!(f, "{id:?} refers to a value that was already borrowed")
+            }
+            ArenaError::ForeignArena(id) => {
+                write!(f, "{id:?} was used with an arena that did not create it")
+            }
+            ArenaError::BorrowConflict(id) => {
+                write!(
+                    f,
+                    "{id:?} refers to a value that is borrowed in a conflicting way"
+                )
+            }
+            ArenaError::IndexOutOfBounds { index, len } => {
+                write!(
+                    f,
+                    "index {index} is out of bounds for arena of length {len}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for ArenaError {}
@@ -52,7 +52,7 @@
 //!
 //! ```
 //! # use arena::Arena;
-//! let mut arena = Arena::from(['A', 'B', 'C']);
+//! let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
 //!
 //! let mut iter = arena.iter();
 //! assert_eq!(iter.next(), Some(&'A'));
@@ -92,6 +92,21 @@
 //! assert_eq!(ids.next(), None);
 //! ```
 //!
+//! # Keys
+//!
+//! By default, the IDs returned by an `Arena<T>` are of type [`ArenaId<T>`], so an ID
+//! can only be used with arenas of the value type it was created for. When multiple
+//! arenas store the same value type, distinct key types can be declared with the
+//! [`new_key_type!`] macro to keep them apart. All keys can be converted to and from
+//! an untyped [`RawId`] when IDs of different arenas need to be stored together.
+//!
+//! # Features
+//!
+//! - `serde`: implements `Serialize` and `Deserialize` for [`Arena`].
+//! - `instance-tags`: stamps every ID with a tag of the arena that created it, so that
+//!   using an ID with the wrong arena panics instead of accessing an unrelated value.
+//!   This makes IDs 4 bytes larger.
+//!
 //! # Performance
 //!
 //! Lookups by ID do a few checks, so they are slower than `Vec<T>` indexing, but like
@@ -102,18 +117,82 @@
 //! the last value will get moved into the removed value's position. The ID of that value
 //! will then get remapped to prevent it from being invalidated. Because of this, you
 //! should never assume the values or IDs in an arena remain in the order you added them.
+//!
+//! When the order of the values matters, [`remove_stable`](Arena::remove_stable) can be
+//! used instead, which shifts the following values down like [`Vec::remove`]. An arena
+//! can also be set to always [preserve order](Arena::set_preserve_order) when removing.
+
+mod branded;
+mod bucketed;
+mod capacity;
+mod cell;
+mod dedup;
+mod drain;
+mod entry;
+mod error;
+mod remap;
+mod reorder;
+mod split;
+
+pub use branded::{BrandedId, Scope};
+pub use bucketed::BucketedArena;
+pub use capacity::GrowthPolicy;
+pub use cell::{ArenaCell, CellRef, CellRefMut};
+pub use drain::{Drain, ExtractIf};
+pub use entry::{Entry, OccupiedEntry, VacantEntry, VacantReason};
+pub use error::ArenaError;
+pub use remap::{IdRemap, RemapIter};
+pub use reorder::PermutationError;
+pub use split::RestMut;
 
 use std::cmp::Ordering;
-use std::ops::{Deref, Index, IndexMut};
+use std::collections::TryReserveError;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::num::NonZeroU32;
+use std::ops::{Bound, Deref, Index, IndexMut, Range, RangeBounds};
 
 /// A contiguous growable container which assigns and returns IDs to values when they are
 /// added to it.
+///
+/// The IDs handed out are of the key type `K`, which defaults to [`ArenaId<T>`]. Because
+/// the key carries the type of the arena it came from, passing an ID from one kind of
+/// arena to another is a compile error. Distinct key types can also be declared for
+/// arenas that store the same value type with the [`new_key_type!`] macro.
+///
+/// # Uniqueness
+///
+/// Every slot of the arena has its own 32-bit generation, which is bumped each time the
+/// slot's value is removed. An ID is made of a slot index and the generation that slot
+/// had when the value was inserted, so IDs are unique per slot for as long as the slot
+/// is in use: an ID to a removed value will never match a value inserted after it.
+///
+/// When a slot's generation is exhausted, the slot is retired instead of freed, and it
+/// is never used again. Retired slots are still counted by [`slot_count`](Self::slot_count)
+/// and are reported by [`retired_slot_count`](Self::retired_slot_count).
+///
+/// # Instance tags
+///
+/// Two arenas of the same type hand out IDs that look alike, so by default nothing stops
+/// an ID from one arena being used with another, which will access an unrelated value.
+/// When the `instance-tags` feature is enabled, every arena is given a unique tag that is
+/// stored in the IDs it creates, and looking up an ID created by a different arena will
+/// panic. Cloning an arena keeps its tag, so IDs remain usable with both the original
+/// and the clone.
 #[derive(Debug, Clone)]
-pub struct Arena<T> {
+pub struct Arena<T, K = ArenaId<T>> {
     values: Vec<T>,
     slots: Vec<Slot>,
-    next_uid: u64,
     first_free: Option<usize>,
+    retired: usize,
+    base_generation: NonZeroU32,
+    tag: InstanceTag,
+    preserve_order: bool,
+    partition: usize,
+    growth: GrowthPolicy,
+    _key: PhantomData<fn() -> K>,
 }
 
 impl<T> Arena<T> {
@@ -127,12 +206,7 @@ impl<T> Arena<T> {
     /// let mut arena: Arena<String> = Arena::new();
     /// ```
     pub const fn new() -> Self {
-        Self {
-            values: Vec::new(),
-            slots: Vec::new(),
-            next_uid: 1,
-            first_free: None,
-        }
+        Self::from_parts(Vec::new(), Vec::new(), NonZeroU32::MIN)
     }
 
     /// Constructs a new, empty `Arena<T>` with at least the specified capacity.
@@ -146,11 +220,65 @@ impl<T> Arena<T> {
     /// ```
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_key(capacity)
+    }
+}
+
+impl<T, K: Key> Arena<T, K> {
+    /// Constructs a new, empty `Arena<T, K>` that uses a custom key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(unused_mut)]
+    /// # use arena::{new_key_type, Arena};
+    /// new_key_type! {
+    ///     struct NameId;
+    /// }
+    ///
+    /// let mut arena: Arena<String, NameId> = Arena::with_key();
+    /// ```
+    pub const fn with_key() -> Self {
+        Self::from_parts(Vec::new(), Vec::new(), NonZeroU32::MIN)
+    }
+
+    /// Constructs a new, empty `Arena<T, K>` that uses a custom key type with at
+    /// least the specified capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(unused_mut)]
+    /// # use arena::{new_key_type, Arena};
+    /// new_key_type! {
+    ///     struct NameId;
+    /// }
+    ///
+    /// let mut arena: Arena<String, NameId> = Arena::with_capacity_and_key(1000);
+    /// ```
+    #[inline]
+    pub fn with_capacity_and_key(capacity: usize) -> Self {
+        Self::from_parts(
+            Vec::with_capacity(capacity),
+            Vec::with_capacity(capacity),
+            NonZeroU32::MIN,
+        )
+    }
+
+    /// Constructs an arena from values and slots that already refer to each other,
+    /// with all other settings at their defaults. The free list is left empty.
+    const fn from_parts(values: Vec<T>, slots: Vec<Slot>, base_generation: NonZeroU32) -> Self {
         Self {
-            values: Vec::with_capacity(capacity),
-            slots: Vec::with_capacity(capacity),
-            next_uid: 1,
+            values,
+            slots,
             first_free: None,
+            retired: 0,
+            base_generation,
+            tag: InstanceTag::new(),
+            preserve_order: false,
+            partition: 0,
+            growth: GrowthPolicy::Double,
+            _key: PhantomData,
         }
     }
 
@@ -177,7 +305,7 @@ impl<T> Arena<T> {
     ///
     /// ```
     /// # use arena::Arena;
-    /// let mut arena = Arena::from(['A', 'B', 'C']);
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
     ///
     /// assert_eq!(arena.len(), 3);
     /// assert_eq!(arena.slot_count(), 3);
@@ -199,7 +327,7 @@ impl<T> Arena<T> {
     ///
     /// ```
     /// # use arena::Arena;
-    /// let mut arena = Arena::from(['A', 'B', 'C']);
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
     ///
     /// assert_eq!(arena.slot_count(), 3);
     /// assert_eq!(arena.free_slot_count(), 0);
@@ -211,7 +339,58 @@ impl<T> Arena<T> {
     /// ```
     #[inline]
     pub fn free_slot_count(&self) -> usize {
-        self.slot_count() - self.len()
+        self.slot_count() - self.len() - self.retired
+    }
+
+    /// Returns the amount of slots that have been retired. A slot is retired when
+    /// its generation is exhausted, after which it is never used again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let arena: Arena<_> = Arena::from(['A', 'B', 'C']);
+    ///
+    /// assert_eq!(arena.retired_slot_count(), 0);
+    /// ```
+    #[inline]
+    pub fn retired_slot_count(&self) -> usize {
+        self.retired
+    }
+
+    /// Returns `true` if removing values from the arena preserves the order of
+    /// the remaining values.
+    ///
+    /// See [`set_preserve_order`](Self::set_preserve_order) for more information.
+    #[inline]
+    pub fn preserve_order(&self) -> bool {
+        self.preserve_order
+    }
+
+    /// Sets whether removing values from the arena should preserve the order of
+    /// the remaining values.
+    ///
+    /// By default, [`remove`](Self::remove) and [`remove_at`](Self::remove_at) move
+    /// the last value into the removed value's position, which is fast but changes
+    /// the order of the values. When this is enabled, they will instead behave like
+    /// [`remove_stable`](Self::remove_stable) and
+    /// [`remove_stable_at`](Self::remove_stable_at), shifting all the following values
+    /// down to fill the gap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C', 'D']);
+    /// arena.set_preserve_order(true);
+    ///
+    /// arena.remove_at(1);
+    ///
+    /// assert_eq!(arena.as_slice(), &['A', 'C', 'D']);
+    /// ```
+    #[inline]
+    pub fn set_preserve_order(&mut self, preserve_order: bool) {
+        self.preserve_order = preserve_order;
     }
 
     /// Extracts a slice containing all the arena's values.
@@ -220,7 +399,7 @@ impl<T> Arena<T> {
     ///
     /// ```
     /// # use arena::Arena;
-    /// let mut arena = Arena::from(['A', 'B', 'C']);
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
     ///
     /// assert_eq!(arena.as_slice(), &['A', 'B', 'C']);
     ///
@@ -292,11 +471,33 @@ impl<T> Arena<T> {
     /// assert_eq!(arena.get(c), Some(&'C'));
     /// ```
     #[inline]
-    pub fn get(&self, id: ArenaId) -> Option<&T> {
-        match &self.slots.get(id.idx)?.state {
-            State::Used { uid, value } if *uid == id.uid => Some(&self.values[*value]),
-            _ => None,
-        }
+    pub fn get(&self, id: K) -> Option<&T> {
+        let index = self.lookup(id.into_raw())?;
+        Some(&self.values[index])
+    }
+
+    /// Returns a reference to the value assigned with the ID, or an error describing
+    /// why the value could not be accessed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaError, ArenaId, RawId};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    ///
+    /// assert_eq!(arena.try_get(a), Ok(&'A'));
+    ///
+    /// arena.remove(a);
+    /// assert_eq!(arena.try_get(a), Err(ArenaError::StaleId(a.into())));
+    ///
+    /// let null = ArenaId::NULL;
+    /// assert_eq!(arena.try_get(null), Err(ArenaError::OutOfRange(RawId::NULL)));
+    /// ```
+    #[inline]
+    pub fn try_get(&self, id: K) -> Result<&T, ArenaError> {
+        let index = self.try_lookup(id.into_raw())?;
+        Ok(&self.values[index])
     }
 
     /// Returns a mutable reference to the value assigned with the ID, or `None`
@@ -323,19 +524,104 @@ impl<T> Arena<T> {
     /// assert_eq!(arena.as_slice(), &['B', 'A']);
     /// ```
     #[inline]
-    pub fn get_mut(&mut self, id: ArenaId) -> Option<&mut T> {
-        match &self.slots.get(id.idx)?.state {
-            State::Used { uid, value } if *uid == id.uid => Some(&mut self.values[*value]),
-            _ => None,
+    pub fn get_mut(&mut self, id: K) -> Option<&mut T> {
+        let index = self.lookup(id.into_raw())?;
+        Some(&mut self.values[index])
+    }
+
+    /// Returns a mutable reference to the value assigned with the ID, or an error
+    /// describing why the value could not be accessed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaError};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    ///
+    /// *arena.try_get_mut(a).unwrap() = 'B';
+    /// assert_eq!(arena[a], 'B');
+    ///
+    /// arena.remove(a);
+    /// assert_eq!(arena.try_get_mut(a), Err(ArenaError::StaleId(a.into())));
+    /// ```
+    #[inline]
+    pub fn try_get_mut(&mut self, id: K) -> Result<&mut T, ArenaError> {
+        let index = self.try_lookup(id.into_raw())?;
+        Ok(&mut self.values[index])
+    }
+
+    /// Returns a reference to the value assigned with the ID, without checking that
+    /// the value is in the arena.
+    ///
+    /// For a safe alternative that only checks each ID once, see [`scope`](Self::scope).
+    ///
+    /// # Safety
+    ///
+    /// The ID must be assigned to a value in the arena, meaning that [`get`](Self::get)
+    /// would return `Some`. Calling this with any other ID is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    ///
+    /// assert!(arena.contains(a));
+    /// assert_eq!(unsafe { arena.get_unchecked(a) }, &'A');
+    /// ```
+    #[inline]
+    pub unsafe fn get_unchecked(&self, id: K) -> &T {
+        // SAFETY: the caller guarantees that the ID is assigned to a value
+        unsafe {
+            let index = self.lookup_unchecked(id.into_raw());
+            self.values.get_unchecked(index)
+        }
+    }
+
+    /// Returns a mutable reference to the value assigned with the ID, without checking
+    /// that the value is in the arena.
+    ///
+    /// For a safe alternative that only checks each ID once, see [`scope`](Self::scope).
+    ///
+    /// # Safety
+    ///
+    /// The ID must be assigned to a value in the arena, meaning that
+    /// [`get_mut`](Self::get_mut) would return `Some`. Calling this with any other ID
+    /// is undefined behavior.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    ///
+    /// assert!(arena.contains(a));
+    /// unsafe { *arena.get_unchecked_mut(a) = 'B' };
+    /// assert_eq!(arena[a], 'B');
+    /// ```
+    #[inline]
+    pub unsafe fn get_unchecked_mut(&mut self, id: K) -> &mut T {
+        // SAFETY: the caller guarantees that the ID is assigned to a value
+        unsafe {
+            let index = self.lookup_unchecked(id.into_raw());
+            self.values.get_unchecked_mut(index)
         }
     }
 
     /// Returns a pair of mutable references correspding to the pair of
     /// supplied IDs.
     ///
+    /// # Panics
+    ///
+    /// Panics if both IDs refer to the same value.
+    ///
     /// # Examples
     ///
     /// ```
+    /// # #![allow(deprecated)]
     /// # use arena::Arena;
     /// let mut arena = Arena::new();
     /// let a = arena.insert('A');
@@ -354,15 +640,18 @@ impl<T> Arena<T> {
     /// assert_eq!(arena.as_slice(), &['X', 'Y']);
     ///
     /// ```
-    pub fn get2_mut(&mut self, a: ArenaId, b: ArenaId) -> (Option<&mut T>, Option<&mut T>) {
+    #[deprecated(note = "use `get_disjoint_mut` or `try_get2_mut` instead")]
+    pub fn get2_mut(&mut self, a: K, b: K) -> (Option<&mut T>, Option<&mut T>) {
         match (self.index_of(a), self.index_of(b)) {
+            (Some(i), Some(j)) if i == j => {
+                panic!("`get2_mut` was called with {a:?} and {b:?}, which refer to the same value")
+            }
             (Some(a), Some(b)) => {
-                assert_ne!(a, b);
                 let (lower, upper) = self.values.split_at_mut(a.max(b));
                 let (a, b) = if a < b {
                     (&mut lower[a], &mut upper[0])
                 } else {
-                    (&mut lower[0], &mut upper[b])
+                    (&mut upper[0], &mut lower[b])
                 };
                 (Some(a), Some(b))
             }
@@ -372,6 +661,134 @@ impl<T> Arena<T> {
         }
     }
 
+    /// Returns a pair of mutable references to the values assigned with the IDs, or
+    /// an error if either value is not in the arena or both IDs refer to the same
+    /// value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaError};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    ///
+    /// let (val_b, val_a) = arena.try_get2_mut(b, a).unwrap();
+    /// *val_a = 'X';
+    /// *val_b = 'Y';
+    /// assert_eq!(arena.as_slice(), &['X', 'Y']);
+    ///
+    /// assert_eq!(arena.try_get2_mut(a, a), Err(ArenaError::AliasedIds(a.into())));
+    /// ```
+    pub fn try_get2_mut(&mut self, a: K, b: K) -> Result<(&mut T, &mut T), ArenaError> {
+        let i = self.try_lookup(a.into_raw())?;
+        let j = self.try_lookup(b.into_raw())?;
+        if i == j {
+            return Err(ArenaError::AliasedIds(b.into_raw()));
+        }
+
+        let (lower, upper) = self.values.split_at_mut(i.max(j));
+        if i < j {
+            Ok((&mut lower[i], &mut upper[0]))
+        } else {
+            Ok((&mut upper[0], &mut lower[j]))
+        }
+    }
+
+    /// Returns mutable references to the values assigned with each of the IDs, or
+    /// an error if any value is not in the arena or any two IDs refer to the same
+    /// value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaError};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(1);
+    /// let b = arena.insert(2);
+    /// let c = arena.insert(3);
+    ///
+    /// let [val_c, val_a] = arena.get_disjoint_mut([c, a]).unwrap();
+    /// std::mem::swap(val_c, val_a);
+    /// assert_eq!(arena.as_slice(), &[3, 2, 1]);
+    ///
+    /// assert_eq!(
+    ///     arena.get_disjoint_mut([a, b, a]).unwrap_err(),
+    ///     ArenaError::AliasedIds(a.into())
+    /// );
+    /// ```
+    pub fn get_disjoint_mut<const N: usize>(
+        &mut self,
+        ids: [K; N],
+    ) -> Result<[&mut T; N], ArenaError> {
+        let mut indices = [0; N];
+        for (index, id) in indices.iter_mut().zip(ids) {
+            *index = self.try_lookup(id.into_raw())?;
+        }
+        Self::check_disjoint(&indices, &ids)?;
+
+        // SAFETY: the positions are in bounds by the invariant on `lookup_slots`, and
+        // they were checked to be distinct so the references do not alias
+        let ptr = self.values.as_mut_ptr();
+        Ok(indices.map(|index| unsafe { &mut *ptr.add(index) }))
+    }
+
+    /// Returns mutable references to the values assigned with each of the IDs in the
+    /// slice, or an error if any value is not in the arena or any two IDs refer to
+    /// the same value.
+    ///
+    /// This is like [`get_disjoint_mut`](Self::get_disjoint_mut), but for a number of
+    /// IDs that is only known at runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from([1, 2, 3, 4]);
+    /// let ids: Vec<_> = arena.ids().step_by(2).collect();
+    ///
+    /// for val in arena.get_many_mut(&ids).unwrap() {
+    ///     *val *= 10;
+    /// }
+    /// assert_eq!(arena.as_slice(), &[10, 2, 30, 4]);
+    /// ```
+    pub fn get_many_mut(&mut self, ids: &[K]) -> Result<Vec<&mut T>, ArenaError> {
+        let indices = ids
+            .iter()
+            .map(|id| self.try_lookup(id.into_raw()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::check_disjoint(&indices, ids)?;
+
+        // SAFETY: the positions are in bounds by the invariant on `lookup_slots`, and
+        // they were checked to be distinct so the references do not alias
+        let ptr = self.values.as_mut_ptr();
+        Ok(indices
+            .into_iter()
+            .map(|index| unsafe { &mut *ptr.add(index) })
+            .collect())
+    }
+
+    /// Returns an error with the first ID that refers to the same position as an
+    /// earlier one.
+    fn check_disjoint(indices: &[usize], ids: &[K]) -> Result<(), ArenaError> {
+        let aliased = if indices.len() <= 8 {
+            // compare every pair when there are only a few
+            (1..indices.len()).find(|&i| indices[..i].contains(&indices[i]))
+        } else {
+            let mut order: Vec<usize> = (0..indices.len()).collect();
+            order.sort_by_key(|&i| (indices[i], i));
+            order
+                .windows(2)
+                .filter(|pair| indices[pair[0]] == indices[pair[1]])
+                .map(|pair| pair[1])
+                .min()
+        };
+        match aliased {
+            Some(i) => Err(ArenaError::AliasedIds(ids[i].into_raw())),
+            None => Ok(()),
+        }
+    }
+
     /// Returns true if the arena contains a value assigned with the ID.
     ///
     /// # Examples
@@ -394,7 +811,7 @@ impl<T> Arena<T> {
     /// assert!(arena.contains(c));
     /// ```
     #[inline]
-    pub fn contains(&self, id: ArenaId) -> bool {
+    pub fn contains(&self, id: K) -> bool {
         self.get(id).is_some()
     }
 
@@ -426,13 +843,16 @@ impl<T> Arena<T> {
     ///
     /// ```
     #[inline]
-    pub fn id_at(&self, index: usize) -> Option<ArenaId> {
+    pub fn id_at(&self, index: usize) -> Option<K> {
         if index >= self.len() {
             return None;
         }
         let idx = self.slots.get(index)?.value_slot;
-        match &self.slots[idx].state {
-            State::Used { uid, value } if *value == index => Some(ArenaId { uid: *uid, idx }),
+        let slot = &self.slots[idx];
+        match slot.state {
+            State::Used { value } if value == index => {
+                Some(K::from_raw(slot.id(idx, self.tag.get())))
+            }
             _ => None,
         }
     }
@@ -464,19 +884,48 @@ impl<T> Arena<T> {
     ///
     /// ```
     #[inline]
-    pub fn index_of(&self, id: ArenaId) -> Option<usize> {
-        match &self.slots.get(id.idx)?.state {
-            State::Used { uid, value } if *uid == id.uid => Some(*value),
-            _ => None,
+    pub fn index_of(&self, id: K) -> Option<usize> {
+        self.lookup(id.into_raw())
+    }
+
+    /// Returns the position of the value assigned with the ID, or `None` if it is not
+    /// in the arena. Panics if the ID was created by a different arena.
+    #[inline]
+    fn lookup(&self, id: RawId) -> Option<usize> {
+        match self.try_lookup(id) {
+            Ok(index) => Some(index),
+            Err(err @ ArenaError::ForeignArena(_)) => panic!("{err}"),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the position of the value assigned with the ID, without checking its
+    /// slot or generation. The ID must be assigned to a value in the arena.
+    #[inline]
+    unsafe fn lookup_unchecked(&self, id: RawId) -> usize {
+        unsafe {
+            match self.slots.get_unchecked(id.slot()).state {
+                State::Used { value } => value,
+                _ => std::hint::unreachable_unchecked(),
+            }
         }
     }
 
+    #[inline]
+    fn try_lookup(&self, id: RawId) -> Result<usize, ArenaError> {
+        lookup_slots(&self.slots, self.tag.get(), id)
+    }
+
     /// Returns the ID that will be assigned to the next inserted value.
     #[inline]
-    pub fn next_id(&self) -> ArenaId {
-        ArenaId {
-            idx: self.first_free.unwrap_or(self.slots.len()),
-            uid: self.next_uid,
+    pub fn next_id(&self) -> K {
+        match self.first_free {
+            Some(idx) => K::from_raw(self.slots[idx].id(idx, self.tag.get())),
+            None => K::from_raw(RawId::new(
+                self.slots.len(),
+                self.base_generation,
+                self.tag.get(),
+            )),
         }
     }
 
@@ -496,7 +945,7 @@ impl<T> Arena<T> {
     /// assert_eq!(arena.get(b), Some(&'B'));
     /// ```
     #[inline]
-    pub fn insert(&mut self, value: T) -> ArenaId {
+    pub fn insert(&mut self, value: T) -> K {
         self.insert_with(|_| value)
     }
 
@@ -504,12 +953,14 @@ impl<T> Arena<T> {
     /// function is passed the ID assigned to the value, which is useful if
     /// the values themselves want to store the IDs on construction.
     ///
+    /// If the function panics, the arena is left unchanged.
+    ///
     /// # Examples
     ///
     /// ```
     /// # use arena::{Arena, ArenaId};
     /// struct Person {
-    ///     id: ArenaId,
+    ///     id: ArenaId<Person>,
     ///     name: &'static str,
     /// }
     ///
@@ -531,72 +982,167 @@ impl<T> Arena<T> {
     /// assert_eq!(arena[bar].id, bar);
     /// assert_eq!(arena[bar].name, "Bar");
     /// ```
-    pub fn insert_with<F>(&mut self, create: F) -> ArenaId
+    pub fn insert_with<F>(&mut self, create: F) -> K
     where
-        F: FnOnce(ArenaId) -> T,
+        F: FnOnce(K) -> T,
     {
-        let value = self.values.len();
+        if self.first_free.is_none() {
+            assert!(
+                self.slots.len() < MAX_SLOTS,
+                "arena cannot hold more than {MAX_SLOTS} slots"
+            );
+        }
+
+        // create the value before the slot is claimed, so that if `create` panics,
+        // the arena is left unchanged
+        let id = self.next_id();
+        let value = create(id);
+
+        self.grow_for_insert();
+        let index = self.values.len();
         let idx = match self.first_free.take() {
             Some(idx) => {
-                match &self.slots[idx].state {
+                match self.slots[idx].state {
                     State::Free { next_free } => {
-                        self.first_free = *next_free;
+                        self.first_free = next_free;
                     }
                     _ => unreachable!(),
                 }
-                self.slots[idx].state = State::Used {
-                    uid: self.next_uid,
-                    value,
-                };
+                self.slots[idx].state = State::Used { value: index };
                 idx
             }
             None => {
                 let idx = self.slots.len();
                 self.slots.push(Slot {
                     value_slot: 0,
-                    state: State::Used {
-                        uid: self.next_uid,
-                        value,
-                    },
+                    generation: self.base_generation,
+                    state: State::Used { value: index },
                 });
                 idx
             }
         };
-        self.slots[value].value_slot = idx;
-        let id = ArenaId {
-            uid: self.next_uid,
-            idx,
-        };
-        self.next_uid += 1;
-        self.values.push(create(id));
+        self.slots[index].value_slot = idx;
+        self.values.push(value);
         id
     }
 
+    /// Tries to insert a value in the arena, returning the ID assigned to the value,
+    /// or an error if the arena could not allocate space for it. If an error is
+    /// returned, the values and IDs in the arena are left unchanged, although the
+    /// capacity of the values may have grown.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena already has the maximum number of slots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.try_insert('A').expect("out of memory");
+    ///
+    /// assert_eq!(arena[a], 'A');
+    /// ```
+    pub fn try_insert(&mut self, value: T) -> Result<K, TryReserveError> {
+        self.try_grow_for_insert()?;
+        Ok(self.insert(value))
+    }
+
+    /// Tries to insert a value, created by the provided function, to the arena. The
+    /// function is passed the ID that will be assigned to the value, and if it returns
+    /// an error, the error is returned and the arena is left unchanged.
+    ///
+    /// Because nothing was inserted, the ID passed to a function that fails will be
+    /// assigned to the next value inserted in the arena instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena already has the maximum number of slots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaId};
+    /// struct Person {
+    ///     id: ArenaId<Person>,
+    ///     age: u32,
+    /// }
+    ///
+    /// let mut arena = Arena::new();
+    ///
+    /// let result = arena.try_insert_with(|id| {
+    ///     let age = "abc".parse()?;
+    ///     Ok::<_, std::num::ParseIntError>(Person { id, age })
+    /// });
+    /// assert!(result.is_err());
+    /// assert!(arena.is_empty());
+    ///
+    /// let foo = arena
+    ///     .try_insert_with(|id| {
+    ///         let age = "42".parse()?;
+    ///         Ok::<_, std::num::ParseIntError>(Person { id, age })
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(arena[foo].id, foo);
+    /// assert_eq!(arena[foo].age, 42);
+    /// ```
+    pub fn try_insert_with<F, E>(&mut self, create: F) -> Result<K, E>
+    where
+        F: FnOnce(K) -> Result<T, E>,
+    {
+        // the value is created before the arena is modified, so nothing has to be
+        // undone if it fails
+        let id = self.next_id();
+        let value = create(id)?;
+        let inserted = self.insert(value);
+        debug_assert_eq!(inserted, id);
+        Ok(inserted)
+    }
+
     /// Removes the value from the arena assigned to the ID. If the value existed
     /// in the arena, it will be returned.
     ///
+    /// The last value in the arena is moved into the removed value's position, unless
+    /// the arena has been set to [preserve order](Self::set_preserve_order).
+    ///
     /// # Examples
     ///
     /// ```
-    /// # use arena::Arena;
+    /// # use arena::{Arena, ArenaId};
     /// let mut arena = Arena::new();
     /// let foo = arena.insert("foo");
     ///
     /// assert_eq!(arena.remove(foo), Some("foo"));
     /// assert_eq!(arena.remove(foo), None);
     ///
+    /// // IDs to slots the arena does not have are ignored too
+    /// assert_eq!(arena.remove(ArenaId::NULL), None);
     /// ```
-    pub fn remove(&mut self, id: ArenaId) -> Option<T> {
-        // get the position of the removed value
-        let removed_val = match &self.slots[id.idx].state {
-            State::Used { uid, value } if *uid == id.uid => *value,
-            _ => return None,
-        };
+    pub fn remove(&mut self, id: K) -> Option<T> {
+        let id = id.into_raw();
+        let index = self.lookup(id)?;
+        Some(self.remove_value(id.slot(), index))
+    }
+
+    /// Removes the value at the position, which must be assigned to the slot, keeping
+    /// the order of the remaining values if the arena preserves order.
+    fn remove_value(&mut self, slot: usize, index: usize) -> T {
+        if self.preserve_order {
+            return self.remove_value_stable(slot, index);
+        }
+        let mut removed_val = index;
+
+        // if the value is in the partition, swap it to the end of the partition first
+        // so the partition stays contiguous
+        if removed_val < self.partition {
+            self.partition -= 1;
+            self.swap(removed_val, self.partition);
+            removed_val = self.partition;
+        }
 
         // free up the slot of the removed value
-        self.slots[id.idx].state = State::Free {
-            next_free: self.first_free.replace(id.idx),
-        };
+        self.free_slot(slot);
 
         // check if the removed value is the last in the list
         let last_val = self.values.len() - 1;
@@ -604,25 +1150,41 @@ impl<T> Arena<T> {
             // if not, move the last value into the removed value's slot
             let last_slot = self.slots[last_val].value_slot;
             self.slots[removed_val].value_slot = last_slot;
-            match &mut self.slots[last_slot].state {
-                State::Used { value, .. } => *value = removed_val,
-                _ => unreachable!(),
-            }
-
-            // then also move the value into the removed value's position
-            Some(self.values.swap_remove(removed_val))
-        } else {
-            self.pop()
+            self.set_value(last_slot, removed_val);
         }
+
+        // then also move the value into the removed value's position
+        self.values.swap_remove(removed_val)
     }
 
-    /// Removes the value at the specified index and returns it.
+    /// Removes the value from the arena assigned to the ID and returns it, or returns
+    /// an error describing why the value could not be removed.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use arena::Arena;
-    /// let mut arena = Arena::from(['A', 'B', 'C']);
+    /// # use arena::{Arena, ArenaError};
+    /// let mut arena = Arena::new();
+    /// let foo = arena.insert("foo");
+    ///
+    /// assert_eq!(arena.try_remove(foo), Ok("foo"));
+    /// assert_eq!(arena.try_remove(foo), Err(ArenaError::StaleId(foo.into())));
+    /// ```
+    pub fn try_remove(&mut self, id: K) -> Result<T, ArenaError> {
+        self.try_lookup(id.into_raw())?;
+        match self.remove(id) {
+            Some(value) => Ok(value),
+            None => unreachable!(),
+        }
+    }
+
+    /// Removes the value at the specified index and returns it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
     ///
     /// assert_eq!(arena.remove_at(5), None);
     /// assert_eq!(arena.remove_at(1), Some('B'));
@@ -635,13 +1197,82 @@ impl<T> Arena<T> {
         self.remove(self.id_at(index)?)
     }
 
+    /// Removes the value from the arena assigned to the ID, shifting all values
+    /// after it down to preserve their order. If the value existed in the arena,
+    /// it will be returned.
+    ///
+    /// Because this shifts the following values, it takes *O*(*n* - *i*) time,
+    /// where *i* is the position of the removed value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    /// let d = arena.insert('D');
+    ///
+    /// assert_eq!(arena.remove_stable(b), Some('B'));
+    /// assert_eq!(arena.remove_stable(b), None);
+    ///
+    /// // the values after `B` kept their order, and their IDs are still valid
+    /// assert_eq!(arena.as_slice(), &['A', 'C', 'D']);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[c], 'C');
+    /// assert_eq!(arena[d], 'D');
+    /// ```
+    pub fn remove_stable(&mut self, id: K) -> Option<T> {
+        let id = id.into_raw();
+        let index = self.lookup(id)?;
+        Some(self.remove_value_stable(id.slot(), index))
+    }
+
+    /// Removes the value at the position, which must be assigned to the slot, shifting
+    /// all values after it down to preserve their order.
+    fn remove_value_stable(&mut self, slot: usize, removed_val: usize) -> T {
+        self.free_slot(slot);
+        if removed_val < self.partition {
+            self.partition -= 1;
+        }
+
+        // shift the back-references of all following values down
+        for i in removed_val..self.values.len() - 1 {
+            let slot = self.slots[i + 1].value_slot;
+            self.slots[i].value_slot = slot;
+            self.set_value(slot, i);
+        }
+
+        self.values.remove(removed_val)
+    }
+
+    /// Removes the value at the specified index and returns it, shifting all values
+    /// after it down to preserve their order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C', 'D']);
+    ///
+    /// assert_eq!(arena.remove_stable_at(5), None);
+    /// assert_eq!(arena.remove_stable_at(1), Some('B'));
+    /// assert_eq!(arena.as_slice(), &['A', 'C', 'D']);
+    /// assert_eq!(arena.remove_stable_at(0), Some('A'));
+    /// assert_eq!(arena.as_slice(), &['C', 'D']);
+    /// ```
+    pub fn remove_stable_at(&mut self, index: usize) -> Option<T> {
+        self.remove_stable(self.id_at(index)?)
+    }
+
     /// Pops a value off the end of the arena and returns it.
     ///
     /// # Examples
     ///
     /// ```
     /// # use arena::Arena;
-    /// let mut arena = Arena::from(['A', 'B', 'C']);
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
     ///
     /// assert_eq!(arena.pop(), Some('C'));
     /// assert_eq!(arena.pop(), Some('B'));
@@ -651,27 +1282,71 @@ impl<T> Arena<T> {
     #[inline]
     pub fn pop(&mut self) -> Option<T> {
         let value = self.values.pop()?;
-        let slot = self.slots[self.values.len()].value_slot;
-        self.slots[slot].state = State::Free {
-            next_free: self.first_free.replace(slot),
-        };
+        self.free_slot(self.slots[self.values.len()].value_slot);
+        self.partition = self.partition.min(self.values.len());
         Some(value)
     }
 
-    fn clear_opt(&mut self, clear_slots: bool) {
-        if clear_slots {
-            self.slots.clear();
-            self.first_free = None;
-        } else {
-            for i in 0..self.values.len() {
-                let slot = self.slots[i].value_slot;
-                self.slots[slot].state = State::Free {
-                    next_free: self.first_free.replace(slot),
+    /// Frees the slot so it can be reused, bumping its generation so that IDs
+    /// to the slot's previous value no longer match. If the generation cannot
+    /// be bumped, the slot is retired instead.
+    fn free_slot(&mut self, idx: usize) {
+        let slot = &mut self.slots[idx];
+        match slot.generation.checked_add(1) {
+            Some(generation) => {
+                slot.generation = generation;
+                slot.state = State::Free {
+                    next_free: self.first_free.replace(idx),
                 };
             }
+            None => {
+                slot.state = State::Retired;
+                self.retired += 1;
+            }
+        }
+    }
+
+    /// Removes free slots from the end of the slot list, keeping at least `len`
+    /// slots. Retired slots are never removed, so that their IDs are never reused.
+    fn truncate_slots(&mut self, len: usize) {
+        let len = self.slots[len..]
+            .iter()
+            .rposition(|slot| !matches!(slot.state, State::Free { .. }))
+            .map_or(len, |i| len + i + 1);
+
+        // new slots must start past the generations of the removed ones
+        for slot in &self.slots[len..] {
+            self.base_generation = self.base_generation.max(slot.generation);
+        }
+        self.slots.truncate(len);
+
+        // rebuild the free list so it no longer links to the removed slots
+        self.rebuild_free_list();
+    }
+
+    /// Relinks all free slots in ascending order and recounts the retired ones.
+    fn rebuild_free_list(&mut self) {
+        self.first_free = None;
+        self.retired = 0;
+        for idx in (0..self.slots.len()).rev() {
+            match &mut self.slots[idx].state {
+                State::Free { next_free } => *next_free = self.first_free.replace(idx),
+                State::Retired => self.retired += 1,
+                State::Used { .. } => {}
+            }
         }
+    }
 
+    fn clear_opt(&mut self, clear_slots: bool) {
+        for i in 0..self.values.len() {
+            self.free_slot(self.slots[i].value_slot);
+        }
         self.values.clear();
+        self.partition = 0;
+
+        if clear_slots {
+            self.truncate_slots(0);
+        }
     }
 
     /// Clears all values from the arena. This will free up all the slots,
@@ -681,7 +1356,7 @@ impl<T> Arena<T> {
     ///
     /// ```
     /// # use arena::Arena;
-    /// let mut arena = Arena::from(['A', 'B', 'C']);
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
     ///
     /// assert_eq!(arena.len(), 3);
     /// assert_eq!(arena.slot_count(), 3);
@@ -695,13 +1370,16 @@ impl<T> Arena<T> {
         self.clear_opt(false);
     }
 
-    /// Clears all values and slots from the arena.
+    /// Clears all values from the arena and removes its slots.
+    ///
+    /// Retired slots are kept so that their IDs are never reused, along with any free
+    /// slots before the last of them. Without retired slots, no slots are left.
     ///
     /// # Examples
     ///
     /// ```
     /// # use arena::Arena;
-    /// let mut arena = Arena::from(['A', 'B', 'C']);
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
     ///
     /// assert_eq!(arena.len(), 3);
     /// assert_eq!(arena.slot_count(), 3);
@@ -715,6 +1393,191 @@ impl<T> Arena<T> {
         self.clear_opt(true);
     }
 
+    /// Moves the values into the lowest free slots, then removes all trailing free
+    /// slots and shrinks the slot list. Returns a remap from the old IDs of the moved
+    /// values to their new IDs, which should be used to update any stored IDs.
+    ///
+    /// The old IDs of the moved values no longer match any value, and the values keep
+    /// their positions. Retired slots are never moved or removed, so they may keep
+    /// some slots from being removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let ids: Vec<_> = (0..100).map(|i| arena.insert(i)).collect();
+    /// for &id in &ids[..98] {
+    ///     arena.remove(id);
+    /// }
+    /// assert_eq!(arena.slot_count(), 100);
+    ///
+    /// let remap = arena.compact_slots();
+    ///
+    /// assert_eq!(arena.slot_count(), 2);
+    /// assert_eq!(arena.get(ids[99]), None);
+    /// assert_eq!(arena[remap.resolve(ids[98])], 98);
+    /// assert_eq!(arena[remap.resolve(ids[99])], 99);
+    /// ```
+    pub fn compact_slots(&mut self) -> IdRemap<K> {
+        let tag = self.tag.get();
+        let mut remap = IdRemap::new();
+        let (mut lo, mut hi) = (0, self.slots.len());
+        loop {
+            // find the lowest free slot and the highest used one
+            while lo < hi && !matches!(self.slots[lo].state, State::Free { .. }) {
+                lo += 1;
+            }
+            while lo < hi && !matches!(self.slots[hi - 1].state, State::Used { .. }) {
+                hi -= 1;
+            }
+            if lo + 1 >= hi {
+                break;
+            }
+            hi -= 1;
+
+            // the moved value takes the generation of the free slot, which is past
+            // all of the IDs that were previously assigned with it
+            let value = match self.slots[hi].state {
+                State::Used { value } => value,
+                _ => unreachable!(),
+            };
+            let old = K::from_raw(self.slots[hi].id(hi, tag));
+            self.slots[lo].state = State::Used { value };
+            self.slots[value].value_slot = lo;
+            remap.insert(old, K::from_raw(self.slots[lo].id(lo, tag)));
+
+            // free the old slot so its generation is bumped before it gets removed
+            self.free_slot(hi);
+            lo += 1;
+        }
+
+        self.truncate_slots(self.values.len());
+        self.slots.shrink_to_fit();
+        remap
+    }
+
+    /// Removes the free slots from the end of the slot list, without invalidating
+    /// any IDs.
+    ///
+    /// Unlike [`compact_slots`](Self::compact_slots), this does not move any values
+    /// to other slots, so only free slots after the last used or retired slot are
+    /// removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    /// let d = arena.insert('D');
+    ///
+    /// arena.remove(b);
+    /// arena.remove(d);
+    /// arena.trim_free_tail();
+    ///
+    /// assert_eq!(arena.slot_count(), 3);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[c], 'C');
+    /// ```
+    #[inline]
+    pub fn trim_free_tail(&mut self) {
+        self.truncate_slots(self.values.len());
+    }
+
+    /// Retains only the values specified by the predicate, which is passed the ID
+    /// and a reference to each value.
+    ///
+    /// This removes all values for which the predicate returns `false`, in a single
+    /// pass over the arena. The remaining values keep their order and their IDs. If
+    /// the predicate panics, the values that were not tested yet are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(1);
+    /// let b = arena.insert(2);
+    /// let c = arena.insert(3);
+    /// let d = arena.insert(4);
+    ///
+    /// arena.retain(|id, val| id != a && val % 2 == 1);
+    ///
+    /// assert_eq!(arena.as_slice(), &[3]);
+    /// assert_eq!(arena.get(a), None);
+    /// assert_eq!(arena.get(b), None);
+    /// assert_eq!(arena.get(c), Some(&3));
+    /// assert_eq!(arena.get(d), None);
+    /// ```
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(K, &T) -> bool,
+    {
+        self.retain_mut(|id, val| keep(id, val));
+    }
+
+    /// Retains only the values specified by the predicate, which is passed the ID
+    /// and a mutable reference to each value.
+    ///
+    /// This removes all values for which the predicate returns `false`, in a single
+    /// pass over the arena. The remaining values keep their order and their IDs. If
+    /// the predicate panics, the values that were not tested yet are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from([1, 2, 3, 4, 5]);
+    ///
+    /// arena.retain_mut(|_, val| {
+    ///     *val *= 10;
+    ///     *val > 20
+    /// });
+    ///
+    /// assert_eq!(arena.as_slice(), &[30, 40, 50]);
+    /// ```
+    pub fn retain_mut<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(K, &mut T) -> bool,
+    {
+        let tag = self.tag.get();
+        let len = self.values.len();
+
+        // the guard removes the values once the loop is done, or if `keep` panics
+        let mut guard = RetainGuard {
+            arena: self,
+            read: 0,
+            kept: 0,
+            split: 0,
+        };
+        while guard.read < len {
+            let arena = &mut *guard.arena;
+            let i = guard.read;
+            let slot = arena.slots[i].value_slot;
+            let id = K::from_raw(arena.slots[slot].id(slot, tag));
+            if keep(id, &mut arena.values[i]) {
+                if i < arena.partition {
+                    guard.split += 1;
+                }
+
+                // move the kept value down to fill the gap left by the removed ones
+                let kept = guard.kept;
+                if kept < i {
+                    arena.values.swap(kept, i);
+                    arena.slots[kept].value_slot = slot;
+                    arena.set_value(slot, kept);
+                }
+                guard.kept += 1;
+            } else {
+                arena.free_slot(slot);
+            }
+            guard.read += 1;
+        }
+    }
+
     /// Swaps the position of the two values corresponding to the provided IDs without
     /// invalidating them.
     ///
@@ -737,7 +1600,7 @@ impl<T> Arena<T> {
     /// assert_eq!(arena[b], 'B');
     /// ```
     #[inline]
-    pub fn swap_positions(&mut self, i: ArenaId, j: ArenaId) -> bool {
+    pub fn swap_positions(&mut self, i: K, j: K) -> bool {
         if let Some(i) = self.index_of(i) {
             if let Some(j) = self.index_of(j) {
                 self.swap(i, j);
@@ -747,7 +1610,11 @@ impl<T> Arena<T> {
         false
     }
 
-    /// Swaps values from the two positions in the arena without invalidating their IDS.
+    /// Swaps values from the two positions in the arena without invalidating their IDs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either position is out of bounds.
     ///
     /// # Examples
     ///
@@ -769,67 +1636,107 @@ impl<T> Arena<T> {
     /// ```
     #[inline]
     pub fn swap(&mut self, i: usize, j: usize) {
-        assert!(i < self.len());
-        assert!(j < self.len());
+        if let Err(err) = self.try_swap(i, j) {
+            panic!("{err}");
+        }
+    }
+
+    /// Swaps values from the two positions in the arena without invalidating their IDs,
+    /// or returns an error if either position is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaError};
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B']);
+    ///
+    /// assert_eq!(arena.try_swap(0, 1), Ok(()));
+    /// assert_eq!(arena.as_slice(), &['B', 'A']);
+    ///
+    /// assert_eq!(
+    ///     arena.try_swap(0, 2),
+    ///     Err(ArenaError::IndexOutOfBounds { index: 2, len: 2 })
+    /// );
+    /// ```
+    pub fn try_swap(&mut self, i: usize, j: usize) -> Result<(), ArenaError> {
+        let len = self.values.len();
+        for index in [i, j] {
+            if index >= len {
+                return Err(ArenaError::IndexOutOfBounds { index, len });
+            }
+        }
 
         if i == j {
-            return;
+            return Ok(());
         }
 
         self.values.swap(i, j);
         let slot_i = self.slots[i].value_slot;
         let slot_j = self.slots[j].value_slot;
-        match &mut self.slots[slot_i] {
-            Slot {
-                value_slot,
-                state: State::Used { value, .. },
-            } => {
-                *value_slot = slot_j;
-                *value = j;
-            }
-            _ => unreachable!(),
-        };
-        match &mut self.slots[slot_j] {
-            Slot {
-                value_slot,
-                state: State::Used { value, .. },
-            } => {
-                *value_slot = slot_i;
-                *value = i;
-            }
+        self.slots[i].value_slot = slot_j;
+        self.slots[j].value_slot = slot_i;
+        self.set_value(slot_i, j);
+        self.set_value(slot_j, i);
+        Ok(())
+    }
+
+    /// Points the used slot at a new value position.
+    #[inline]
+    fn set_value(&mut self, slot: usize, index: usize) {
+        match &mut self.slots[slot].state {
+            State::Used { value } => *value = index,
             _ => unreachable!(),
-        };
+        }
     }
 
-    fn quicksort<F: FnMut(&T, &T) -> Ordering>(
-        &mut self,
-        low: usize,
-        high: usize,
-        compare: &mut F,
-    ) {
-        if low + 1 >= high.wrapping_add(1) {
-            return;
-        }
-        let p = {
-            let (mut i, mut j) = (low, low);
-            while i <= high {
-                if compare(&self.values[i], &self.values[high]) == Ordering::Greater {
-                    i += 1;
-                } else {
-                    self.swap(i, j);
-                    i += 1;
-                    j += 1;
+    /// Moves the values so that the value at position `perm[i]` ends up at position `i`,
+    /// updating their slots so that their IDs remain valid. This takes *O*(*n*) time.
+    fn permute(&mut self, mut perm: Vec<usize>) {
+        debug_assert_eq!(perm.len(), self.values.len());
+
+        // follow each cycle of the permutation, marking positions that are in place
+        // by pointing them at themselves
+        for start in 0..perm.len() {
+            let mut cur = start;
+            loop {
+                let next = perm[cur];
+                perm[cur] = cur;
+                if next == start || next == cur {
+                    break;
                 }
+                self.values.swap(cur, next);
+                let slot = self.slots[cur].value_slot;
+                self.slots[cur].value_slot = self.slots[next].value_slot;
+                self.slots[next].value_slot = slot;
+                cur = next;
             }
-            j - 1
-        };
-        self.quicksort(low, p.wrapping_sub(1), compare);
-        self.quicksort(p + 1, high, compare);
+        }
+
+        // point all the slots at the new positions of their values
+        for i in 0..self.values.len() {
+            self.set_value(self.slots[i].value_slot, i);
+        }
+    }
+
+    /// Returns the positions of the values, sorted by the provided function.
+    fn sorted_positions<F>(&self, sort: F) -> Vec<usize>
+    where
+        F: FnOnce(&mut [usize], &[T]),
+    {
+        let mut perm: Vec<usize> = (0..self.values.len()).collect();
+        sort(&mut perm, &self.values);
+        perm
     }
 
     /// Sorts the values in the arena, using the provided function, without
     /// invalidating their IDs.
     ///
+    /// This sort is stable (i.e., does not reorder equal values) and *O*(*n* \* log(*n*))
+    /// worst-case. The positions of the values are sorted first, and then the values are
+    /// moved to their sorted positions, so each value is only moved once.
+    ///
+    /// If the comparison function panics, the arena is left unchanged.
+    ///
     /// # Examples
     ///
     /// ```
@@ -851,11 +1758,135 @@ impl<T> Arena<T> {
     /// assert_eq!(arena[b], 'B');
     /// assert_eq!(arena[c], 'C');
     /// ```
-    #[inline]
-    pub fn sort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, mut compare: F) {
-        if self.len() > 1 {
-            self.quicksort(0, self.len() - 1, &mut compare);
-        }
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let perm = self.sorted_positions(|perm, values| {
+            perm.sort_by(|&a, &b| compare(&values[a], &values[b]))
+        });
+        self.permute(perm);
+    }
+
+    /// Sorts the values in the arena with a key extraction function, without
+    /// invalidating their IDs.
+    ///
+    /// This sort is stable (i.e., does not reorder equal values) and *O*(*m* \* *n* \* log(*n*))
+    /// worst-case, where the key function is *O*(*m*).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(-5i32);
+    /// let b = arena.insert(4);
+    /// let c = arena.insert(1);
+    ///
+    /// arena.sort_by_key(|val| val.abs());
+    ///
+    /// assert_eq!(arena.as_slice(), &[1, 4, -5]);
+    /// assert_eq!(arena[a], -5);
+    /// assert_eq!(arena[b], 4);
+    /// assert_eq!(arena[c], 1);
+    /// ```
+    pub fn sort_by_key<U, F>(&mut self, mut f: F)
+    where
+        U: Ord,
+        F: FnMut(&T) -> U,
+    {
+        let perm = self.sorted_positions(|perm, values| perm.sort_by_key(|&i| f(&values[i])));
+        self.permute(perm);
+    }
+
+    /// Sorts the values in the arena with a key extraction function, without
+    /// invalidating their IDs. The key function is called only once per value.
+    ///
+    /// This sort is stable (i.e., does not reorder equal values) and is useful when
+    /// the key function is expensive. See [`slice::sort_by_cached_key`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(-5i32);
+    /// let b = arena.insert(4);
+    /// let c = arena.insert(32);
+    ///
+    /// arena.sort_by_cached_key(|val| val.to_string());
+    ///
+    /// assert_eq!(arena.as_slice(), &[-5, 32, 4]);
+    /// assert_eq!(arena[a], -5);
+    /// assert_eq!(arena[b], 4);
+    /// assert_eq!(arena[c], 32);
+    /// ```
+    pub fn sort_by_cached_key<U, F>(&mut self, mut f: F)
+    where
+        U: Ord,
+        F: FnMut(&T) -> U,
+    {
+        let perm =
+            self.sorted_positions(|perm, values| perm.sort_by_cached_key(|&i| f(&values[i])));
+        self.permute(perm);
+    }
+
+    /// Sorts the values in the arena, using the provided function, without
+    /// invalidating their IDs, but might not preserve the order of equal values.
+    ///
+    /// This sort is unstable (i.e., may reorder equal values) and *O*(*n* \* log(*n*))
+    /// worst-case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let c = arena.insert('C');
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    ///
+    /// arena.sort_unstable_by(|a, b| b.cmp(a));
+    ///
+    /// assert_eq!(arena.as_slice(), &['C', 'B', 'A']);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[b], 'B');
+    /// assert_eq!(arena[c], 'C');
+    /// ```
+    pub fn sort_unstable_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let perm = self.sorted_positions(|perm, values| {
+            perm.sort_unstable_by(|&a, &b| compare(&values[a], &values[b]))
+        });
+        self.permute(perm);
+    }
+
+    /// Sorts the values in the arena with a key extraction function, without
+    /// invalidating their IDs, but might not preserve the order of equal values.
+    ///
+    /// This sort is unstable (i.e., may reorder equal values) and *O*(*m* \* *n* \* log(*n*))
+    /// worst-case, where the key function is *O*(*m*).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from([-5i32, 4, 1, -3, 2]);
+    ///
+    /// arena.sort_unstable_by_key(|val| val.abs());
+    ///
+    /// assert_eq!(arena.as_slice(), &[1, 2, -3, 4, -5]);
+    /// ```
+    pub fn sort_unstable_by_key<U, F>(&mut self, mut f: F)
+    where
+        U: Ord,
+        F: FnMut(&T) -> U,
+    {
+        let perm =
+            self.sorted_positions(|perm, values| perm.sort_unstable_by_key(|&i| f(&values[i])));
+        self.permute(perm);
     }
 
     /// Returns the arena as a simple vector of its values.
@@ -867,7 +1898,7 @@ impl<T> Arena<T> {
     ///
     /// ```
     /// # use arena::Arena;
-    /// let mut arena = Arena::from(['A', 'B', 'C']);
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
     /// arena.insert('D');
     ///
     /// let vec = arena.to_vec();
@@ -885,7 +1916,7 @@ impl<T> Arena<T> {
     ///
     /// ```
     /// # use arena::Arena;
-    /// let mut arena = Arena::from([1, 2, 3]);
+    /// let mut arena: Arena<_> = Arena::from([1, 2, 3]);
     ///
     /// for val in arena.iter_mut() {
     ///     *val *= 10;
@@ -916,10 +1947,13 @@ impl<T> Arena<T> {
     /// assert_eq!(pairs.next(), None);
     /// ```
     #[inline]
-    pub fn pairs(&self) -> Pairs<'_, T> {
+    pub fn pairs(&self) -> Pairs<'_, T, K> {
         Pairs {
             iter: self.values.iter().enumerate(),
             slots: &self.slots,
+            offset: 0,
+            tag: self.tag.get(),
+            _key: PhantomData,
         }
     }
 
@@ -949,10 +1983,13 @@ impl<T> Arena<T> {
     /// }
     /// ```
     #[inline]
-    pub fn pairs_mut(&mut self) -> PairsMut<'_, T> {
+    pub fn pairs_mut(&mut self) -> PairsMut<'_, T, K> {
         PairsMut {
             iter: self.values.iter_mut().enumerate(),
             slots: &self.slots,
+            offset: 0,
+            tag: self.tag.get(),
+            _key: PhantomData,
         }
     }
 
@@ -974,25 +2011,127 @@ impl<T> Arena<T> {
     /// assert_eq!(ids.next(), None);
     /// ```
     #[inline]
-    pub fn ids(&self) -> Ids<'_> {
+    pub fn ids(&self) -> Ids<'_, K> {
         Ids {
-            iter: self.slots[..self.len()].iter().enumerate(),
+            positions: 0..self.values.len(),
+            slots: &self.slots,
+            tag: self.tag.get(),
+            _key: PhantomData,
         }
     }
-}
 
-impl<T: Clone> Arena<T> {
-    /// Adds all values from the slice to the arena.
-    #[inline]
-    pub fn extend_from_slice(&mut self, slice: &[T]) {
-        self.values.reserve(slice.len());
-        self.extend(slice.iter().cloned());
+    /// Returns an iterator over the ID/value pairs in the range of positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the starting point is greater than the end point or if the end point
+    /// is greater than the length of the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    ///
+    /// let mut pairs = arena.pairs_range(1..);
+    /// assert_eq!(pairs.next(), Some((b, &'B')));
+    /// assert_eq!(pairs.next(), Some((c, &'C')));
+    /// assert_eq!(pairs.next(), None);
+    /// ```
+    pub fn pairs_range<R>(&self, range: R) -> Pairs<'_, T, K>
+    where
+        R: RangeBounds<usize>,
+    {
+        let range = check_range(range, self.values.len());
+        Pairs {
+            offset: range.start,
+            iter: self.values[range].iter().enumerate(),
+            slots: &self.slots,
+            tag: self.tag.get(),
+            _key: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the values in the arena.
+    ///
+    /// This is the same as calling `iter` on the slice of values, and is named to match
+    /// [`pairs`](Self::pairs) and [`ids`](Self::ids).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let arena: Arena<_> = Arena::from([1, 2, 3]);
+    /// assert_eq!(arena.values().sum::<i32>(), 6);
+    /// ```
+    #[inline]
+    pub fn values(&self) -> std::slice::Iter<'_, T> {
+        self.values.iter()
+    }
+
+    /// Returns an iterator that allows modifying each value in the arena.
+    ///
+    /// This is the same as [`iter_mut`](Self::iter_mut).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from([1, 2, 3]);
+    ///
+    /// for val in arena.values_mut() {
+    ///     *val += 1;
+    /// }
+    ///
+    /// assert_eq!(arena.as_slice(), &[2, 3, 4]);
+    /// ```
+    #[inline]
+    pub fn values_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.values.iter_mut()
+    }
+
+    /// Returns an owning iterator over all ID/value pairs in the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    ///
+    /// let pairs: Vec<_> = arena.into_pairs().collect();
+    /// assert_eq!(pairs, vec![(a, 'A'), (b, 'B')]);
+    /// ```
+    #[inline]
+    pub fn into_pairs(self) -> IntoPairs<T, K> {
+        IntoPairs {
+            tag: self.tag.get(),
+            iter: self.values.into_iter().enumerate(),
+            slots: self.slots,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<T: Clone, K: Key> Arena<T, K> {
+    /// Adds all values from the slice to the arena.
+    #[inline]
+    pub fn extend_from_slice(&mut self, slice: &[T]) {
+        self.values.reserve(slice.len());
+        self.extend(slice.iter().cloned());
     }
 }
 
-impl<T: Ord> Arena<T> {
+impl<T: Ord, K: Key> Arena<T, K> {
     /// Sorts the values in the arena, without invalidating their IDs.
     ///
+    /// This sort is stable (i.e., does not reorder equal values) and *O*(*n* \* log(*n*))
+    /// worst-case.
+    ///
     /// # Examples
     ///
     /// ```
@@ -1013,20 +2152,48 @@ impl<T: Ord> Arena<T> {
     /// assert_eq!(arena[a], 'A');
     /// assert_eq!(arena[b], 'B');
     /// assert_eq!(arena[c], 'C');
+    /// ```
     #[inline]
     pub fn sort(&mut self) {
         self.sort_by(|a, b| a.cmp(b));
     }
+
+    /// Sorts the values in the arena, without invalidating their IDs, but might
+    /// not preserve the order of equal values.
+    ///
+    /// This sort is unstable (i.e., may reorder equal values) and *O*(*n* \* log(*n*))
+    /// worst-case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let c = arena.insert('C');
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    ///
+    /// arena.sort_unstable();
+    ///
+    /// assert_eq!(arena.as_slice(), &['A', 'B', 'C']);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[b], 'B');
+    /// assert_eq!(arena[c], 'C');
+    /// ```
+    #[inline]
+    pub fn sort_unstable(&mut self) {
+        self.sort_unstable_by(|a, b| a.cmp(b));
+    }
 }
 
-impl<T> Default for Arena<T> {
+impl<T, K: Key> Default for Arena<T, K> {
     #[inline]
     fn default() -> Self {
-        Self::new()
+        Self::with_key()
     }
 }
 
-impl<T> Deref for Arena<T> {
+impl<T, K> Deref for Arena<T, K> {
     type Target = [T];
 
     #[inline]
@@ -1035,23 +2202,29 @@ impl<T> Deref for Arena<T> {
     }
 }
 
-impl<T> Index<ArenaId> for Arena<T> {
+impl<T, K: Key> Index<K> for Arena<T, K> {
     type Output = T;
 
     #[inline]
-    fn index(&self, index: ArenaId) -> &Self::Output {
-        self.get(index).unwrap()
+    fn index(&self, index: K) -> &Self::Output {
+        match self.try_get(index) {
+            Ok(value) => value,
+            Err(err) => panic!("{err}"),
+        }
     }
 }
 
-impl<T> IndexMut<ArenaId> for Arena<T> {
+impl<T, K: Key> IndexMut<K> for Arena<T, K> {
     #[inline]
-    fn index_mut(&mut self, index: ArenaId) -> &mut Self::Output {
-        self.get_mut(index).unwrap()
+    fn index_mut(&mut self, index: K) -> &mut Self::Output {
+        match self.try_get_mut(index) {
+            Ok(value) => value,
+            Err(err) => panic!("{err}"),
+        }
     }
 }
 
-impl<T> Extend<T> for Arena<T> {
+impl<T, K: Key> Extend<T> for Arena<T, K> {
     #[inline]
     fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
         for val in iter {
@@ -1060,55 +2233,53 @@ impl<T> Extend<T> for Arena<T> {
     }
 }
 
-impl<'a, T: Clone + 'a> Extend<&'a T> for Arena<T> {
+impl<'a, T: Clone + 'a, K: Key> Extend<&'a T> for Arena<T, K> {
     #[inline]
     fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
         self.extend(iter.into_iter().cloned())
     }
 }
 
-impl<T> From<Vec<T>> for Arena<T> {
+impl<T, K: Key> From<Vec<T>> for Arena<T, K> {
     fn from(values: Vec<T>) -> Self {
-        let mut slots = Vec::new();
-        let mut uid = 0;
+        assert!(
+            values.len() <= MAX_SLOTS,
+            "arena cannot hold more than {MAX_SLOTS} slots"
+        );
+        let mut slots = Vec::with_capacity(values.len());
         for i in 0..values.len() {
             slots.push(Slot {
                 value_slot: i,
-                state: State::Used { uid: uid, value: i },
+                generation: NonZeroU32::MIN,
+                state: State::Used { value: i },
             });
-            uid += 1;
-        }
-        Self {
-            values,
-            slots,
-            first_free: None,
-            next_uid: uid,
         }
+        Self::from_parts(values, slots, NonZeroU32::MIN)
     }
 }
 
-impl<'a, T: Clone + 'a> From<&'a [T]> for Arena<T> {
+impl<'a, T: Clone + 'a, K: Key> From<&'a [T]> for Arena<T, K> {
     #[inline]
     fn from(values: &'a [T]) -> Self {
         Self::from_iter(values.iter().cloned())
     }
 }
 
-impl<'a, T: Clone + 'a> From<&'a mut [T]> for Arena<T> {
+impl<'a, T: Clone + 'a, K: Key> From<&'a mut [T]> for Arena<T, K> {
     #[inline]
     fn from(values: &'a mut [T]) -> Self {
         Self::from_iter(values.iter().cloned())
     }
 }
 
-impl<T, const N: usize> From<[T; N]> for Arena<T> {
+impl<T, K: Key, const N: usize> From<[T; N]> for Arena<T, K> {
     #[inline]
     fn from(values: [T; N]) -> Self {
         Self::from(Vec::from(values))
     }
 }
 
-impl<T> IntoIterator for Arena<T> {
+impl<T, K> IntoIterator for Arena<T, K> {
     type Item = T;
     type IntoIter = std::vec::IntoIter<T>;
 
@@ -1118,11 +2289,31 @@ impl<T> IntoIterator for Arena<T> {
     }
 }
 
-impl<T> FromIterator<T> for Arena<T> {
+impl<'a, T, K> IntoIterator for &'a Arena<T, K> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+impl<'a, T, K> IntoIterator for &'a mut Arena<T, K> {
+    type Item = &'a mut T;
+    type IntoIter = std::slice::IterMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter_mut()
+    }
+}
+
+impl<T, K: Key> FromIterator<T> for Arena<T, K> {
     #[inline]
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
-        let mut arena = Arena::new();
-        arena.extend(iter.into_iter());
+        let mut arena = Arena::with_key();
+        arena.extend(iter);
         arena
     }
 }
@@ -1130,267 +2321,1934 @@ impl<T> FromIterator<T> for Arena<T> {
 #[derive(Debug, Clone)]
 struct Slot {
     value_slot: usize,
+    generation: NonZeroU32,
     state: State,
 }
 
+impl Slot {
+    #[inline]
+    fn id(&self, idx: usize, tag: Tag) -> RawId {
+        RawId::new(idx, self.generation, tag)
+    }
+}
+
 #[derive(Debug, Clone)]
 enum State {
-    Used { uid: u64, value: usize },
+    Used { value: usize },
     Free { next_free: Option<usize> },
+    Retired,
 }
 
-/// An ID assigned to a value when it was added to an arena.
+/// A type that can be used as the key of an [`Arena`].
 ///
-/// Unlike an index, this ID will remain a valid handle to the value even
-/// if other values are removed from the arena and the value vector gets
-/// re-ordered.
+/// Keys are thin wrappers around a [`RawId`]. This trait is implemented by
+/// [`ArenaId<T>`], and by any key type declared with the [`new_key_type!`] macro.
+pub trait Key: Copy + Eq + Ord + Hash + Debug {
+    /// Creates a key from a raw ID.
+    fn from_raw(raw: RawId) -> Self;
+
+    /// Returns the raw ID wrapped by this key.
+    fn into_raw(self) -> RawId;
+}
+
+/// An untyped ID assigned to a value when it was added to an arena.
 ///
-/// They implement `Copy` and so can be passed around freely.
-#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Hash)]
-pub struct ArenaId {
-    uid: u64,
-    idx: usize,
+/// Every key type can be converted to and from a `RawId`, which makes it useful
+/// for storing IDs of different arenas together in the same container. Nothing
+/// prevents a `RawId` from being converted into the key of a different arena,
+/// so converting back should only be done when the origin of the ID is known.
+///
+/// # Examples
+///
+/// ```
+/// # use arena::{Arena, ArenaId, RawId};
+/// let mut chars = Arena::new();
+/// let mut nums = Arena::new();
+///
+/// let a = chars.insert('A');
+/// let one = nums.insert(1);
+///
+/// // erase the IDs so they can be stored together
+/// let raw: Vec<RawId> = vec![a.into(), one.into()];
+///
+/// // and restore them to their original key types
+/// assert_eq!(chars[ArenaId::from(raw[0])], 'A');
+/// assert_eq!(nums[ArenaId::from(raw[1])], 1);
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct RawId {
+    idx: u32,
+    generation: NonZeroU32,
+    tag: Tag,
 }
 
-impl PartialOrd for ArenaId {
+/// The maximum amount of slots an arena can have. The last slot index is never
+/// assigned, which is what makes [`RawId::NULL`] impossible to match.
+const MAX_SLOTS: usize = u32::MAX as usize;
+
+impl RawId {
+    /// An ID that will never be assigned to a value.
+    ///
+    /// This is what IDs are initialized to by default.
+    pub const NULL: Self = Self {
+        idx: u32::MAX,
+        generation: NonZeroU32::MAX,
+        tag: Tag::NONE,
+    };
+
     #[inline]
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+    fn new(idx: usize, generation: NonZeroU32, tag: Tag) -> Self {
+        debug_assert!(idx < MAX_SLOTS);
+        Self {
+            idx: idx as u32,
+            generation,
+            tag,
+        }
     }
-}
 
-impl Ord for ArenaId {
     #[inline]
-    fn cmp(&self, other: &Self) -> Ordering {
-        (self.uid, self.idx).cmp(&(other.uid, other.idx))
+    fn slot(self) -> usize {
+        self.idx as usize
+    }
+
+    /// Returns `true` if this is the [`NULL`](Self::NULL) ID.
+    #[inline]
+    pub fn is_null(self) -> bool {
+        self == Self::NULL
+    }
+
+    fn debug_fmt(&self, name: &str, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct(name);
+        d.field("idx", &self.idx);
+        d.field("generation", &self.generation);
+        #[cfg(feature = "instance-tags")]
+        d.field("tag", &self.tag);
+        d.finish()
     }
 }
 
-/// Iterator over an arena's ID/value pairs.
-///
-/// This struct is created by the [`pairs`](Arena::pairs) method on [`Arena`].
-pub struct Pairs<'a, T> {
-    iter: std::iter::Enumerate<std::slice::Iter<'a, T>>,
-    slots: &'a [Slot],
+impl Debug for RawId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.debug_fmt("RawId", f)
+    }
 }
 
-impl<'a, T> Iterator for Pairs<'a, T> {
-    type Item = (ArenaId, &'a T);
+impl Default for RawId {
+    #[inline]
+    fn default() -> Self {
+        Self::NULL
+    }
+}
 
+impl PartialOrd for RawId {
     #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        let (idx, val) = self.iter.next()?;
-        let idx = self.slots[idx].value_slot;
-        match &self.slots[idx].state {
-            State::Used { uid, .. } => Some((ArenaId { uid: *uid, idx }, val)),
-            _ => unreachable!(),
-        }
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-/// Mutable iterator over an arena's ID/value pairs.
-///
-/// This struct is created by the [`pairs_mut`](Arena::pairs_mut) method on [`Arena`].
-pub struct PairsMut<'a, T> {
-    iter: std::iter::Enumerate<std::slice::IterMut<'a, T>>,
-    slots: &'a [Slot],
+impl Ord for RawId {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.generation, self.idx, self.tag).cmp(&(other.generation, other.idx, other.tag))
+    }
 }
 
-impl<'a, T> Iterator for PairsMut<'a, T> {
-    type Item = (ArenaId, &'a mut T);
+impl Key for RawId {
+    #[inline]
+    fn from_raw(raw: RawId) -> Self {
+        raw
+    }
 
     #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        let (idx, val) = self.iter.next()?;
-        let idx = self.slots[idx].value_slot;
-        match &self.slots[idx].state {
-            State::Used { uid, .. } => Some((ArenaId { uid: *uid, idx }, val)),
-            _ => unreachable!(),
-        }
+    fn into_raw(self) -> RawId {
+        self
     }
 }
 
-/// Iterator over an arena's IDs.
-///
-/// This struct is created by the [`ids`](Arena::ids) method on [`Arena`].
-pub struct Ids<'a> {
-    iter: std::iter::Enumerate<std::slice::Iter<'a, Slot>>,
+/// Identifies the arena that created an ID. This is only stored when the
+/// `instance-tags` feature is enabled, otherwise it takes up no space.
+#[derive(Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+struct Tag {
+    #[cfg(feature = "instance-tags")]
+    value: u32,
 }
 
-impl<'a> Iterator for Ids<'a> {
-    type Item = ArenaId;
+impl Tag {
+    /// The tag of IDs that were not created by an arena, such as [`RawId::NULL`].
+    const NONE: Self = Self {
+        #[cfg(feature = "instance-tags")]
+        value: 0,
+    };
+}
 
-    #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        let (idx, slot) = self.iter.next()?;
-        match &slot.state {
-            State::Used { uid, .. } => Some(ArenaId { uid: *uid, idx }),
-            _ => None,
-        }
+impl Debug for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        #[cfg(feature = "instance-tags")]
+        return self.value.fmt(f);
+        #[cfg(not(feature = "instance-tags"))]
+        return f.write_str("None");
     }
 }
 
-#[cfg(feature = "serde")]
-mod ser {
-    use crate::State;
-    use serde::de::Visitor;
-    use serde::ser::SerializeStruct;
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
-    use std::fmt::Formatter;
-
-    impl<T: Serialize> Serialize for crate::Arena<T> {
-        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-        where
-            S: Serializer,
-        {
-            let mut s = serializer.serialize_struct("Arena", 2)?;
-            s.serialize_field("next_uid", &self.next_uid)?;
-
-            let entries: Vec<Entry<'_, T>> = self
-                .pairs()
-                .map(|(id, val)| Entry {
-                    uid: id.uid,
-                    idx: id.idx,
-                    val,
-                })
-                .collect();
-            s.serialize_field("entries", &entries)?;
+/// The tag of an arena, which is assigned the first time the arena creates an
+/// ID. This allows arenas to still be constructed in a `const` context.
+#[derive(Default)]
+struct InstanceTag {
+    #[cfg(feature = "instance-tags")]
+    value: std::sync::atomic::AtomicU32,
+}
 
-            s.end()
+impl InstanceTag {
+    const fn new() -> Self {
+        Self {
+            #[cfg(feature = "instance-tags")]
+            value: std::sync::atomic::AtomicU32::new(0),
         }
     }
 
-    impl<'de, T: Deserialize<'de>> Deserialize<'de> for crate::Arena<T> {
-        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-        where
-            D: Deserializer<'de>,
-        {
-            let mut de: DeArena<T> = DeArena::deserialize(deserializer)?;
-
-            de.entries.sort_by(|a, b| a.idx.cmp(&b.idx));
-
-            let mut slots = Vec::new();
-            let mut next_value_slot = 0;
-            let mut next_value = 0;
-
-            let mut first_free = None;
-            for e in &de.entries {
-                // push free slots until we reach the entry's index
-                while slots.len() < e.idx {
-                    slots.push(crate::Slot {
-                        value_slot: de.entries[next_value_slot].idx,
-                        state: crate::State::Free {
-                            next_free: first_free.replace(slots.len()),
-                        },
-                    });
-                    next_value_slot += 1;
-                }
-
-                // insert the entry
-                slots.push(crate::Slot {
-                    value_slot: de.entries[next_value_slot].idx,
-                    state: crate::State::Used {
-                        uid: e.uid,
-                        value: next_value,
-                    },
-                });
+    #[cfg(feature = "instance-tags")]
+    fn get(&self) -> Tag {
+        use std::sync::atomic::{AtomicU32, Ordering::Relaxed};
+        static NEXT_TAG: AtomicU32 = AtomicU32::new(1);
 
-                next_value_slot += 1;
-                next_value += 1;
+        let value = match self.value.load(Relaxed) {
+            0 => {
+                let mut next = NEXT_TAG.fetch_add(1, Relaxed);
+                while next == 0 {
+                    next = NEXT_TAG.fetch_add(1, Relaxed);
+                }
+                match self.value.compare_exchange(0, next, Relaxed, Relaxed) {
+                    Ok(_) => next,
+                    Err(value) => value,
+                }
             }
+            value => value,
+        };
+        Tag { value }
+    }
 
-            let values = de.entries.into_iter().map(|e| e.val).collect();
+    #[cfg(not(feature = "instance-tags"))]
+    #[inline]
+    fn get(&self) -> Tag {
+        Tag::NONE
+    }
+}
 
-            Ok(Self {
-                next_uid: de.next_uid,
-                slots,
-                values,
-                first_free,
-            })
+impl Clone for InstanceTag {
+    /// Clones keep the tag of the original arena, so IDs remain valid in both.
+    fn clone(&self) -> Self {
+        Self {
+            #[cfg(feature = "instance-tags")]
+            value: std::sync::atomic::AtomicU32::new(self.get().value),
         }
     }
+}
 
-    #[derive(Serialize)]
-    struct Entry<'a, T> {
-        uid: u64,
-        idx: usize,
-        val: &'a T,
+impl Debug for InstanceTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
     }
+}
 
-    #[derive(Deserialize)]
-    struct DeEntry<T> {
-        uid: u64,
-        idx: usize,
-        val: T,
-    }
+/// An ID assigned to a value when it was added to an arena.
+///
+/// Unlike an index, this ID will remain a valid handle to the value even
+/// if other values are removed from the arena and the value vector gets
+/// re-ordered.
+///
+/// They implement `Copy` and so can be passed around freely. An ID is made of a
+/// 32-bit slot index and a 32-bit non-zero generation, so it is only 8 bytes
+/// large, and wrapping it in an `Option` does not make it any larger:
+///
+/// ```
+/// # use arena::ArenaId;
+/// # if cfg!(not(feature = "instance-tags")) {
+/// assert_eq!(std::mem::size_of::<ArenaId<String>>(), 8);
+/// assert_eq!(std::mem::size_of::<Option<ArenaId<String>>>(), 8);
+/// # }
+/// ```
+///
+/// With the `instance-tags` feature enabled, IDs also store a 32-bit tag of the arena
+/// that created them, making them 12 bytes large.
+///
+/// The ID is typed by the value it was assigned to, so an ID from one arena
+/// cannot be used to access an arena of a different value type:
+///
+/// ```compile_fail
+/// # use arena::Arena;
+/// let mut chars = Arena::new();
+/// let mut nums: Arena<i32> = Arena::new();
+///
+/// let a = chars.insert('A');
+/// nums.get(a);
+/// ```
+pub struct ArenaId<T> {
+    raw: RawId,
+    _type: PhantomData<fn() -> T>,
+}
 
-    #[derive(Deserialize)]
-    struct DeArena<T> {
-        next_uid: u64,
-        entries: Vec<DeEntry<T>>,
+impl<T> ArenaId<T> {
+    /// An ID that will never be assigned to a value.
+    ///
+    /// This is what IDs are initialized to by default.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, ArenaId};
+    /// let arena: Arena<_> = Arena::from(['A', 'B', 'C']);
+    ///
+    /// assert_eq!(ArenaId::<char>::default(), ArenaId::NULL);
+    /// assert_eq!(arena.get(ArenaId::NULL), None);
+    /// ```
+    pub const NULL: Self = Self {
+        raw: RawId::NULL,
+        _type: PhantomData,
+    };
+
+    /// Returns `true` if this is the [`NULL`](Self::NULL) ID.
+    #[inline]
+    pub fn is_null(self) -> bool {
+        self.raw.is_null()
     }
 }
 
-#[test]
-fn rain_test() {
-    let mut arena = Arena::new();
-    let a = arena.insert("a");
-    let b = arena.insert("b");
-    let c = arena.insert("c");
-    let d = arena.insert("d");
-    let e = arena.insert("e");
+impl<T> Debug for ArenaId<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.raw.debug_fmt("ArenaId", f)
+    }
+}
 
-    arena.remove(b);
-    arena.remove(a);
+impl<T> Default for ArenaId<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::NULL
+    }
+}
+
+impl<T> Copy for ArenaId<T> {}
+
+impl<T> Clone for ArenaId<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> PartialEq for ArenaId<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<T> Eq for ArenaId<T> {}
+
+impl<T> Hash for ArenaId<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.raw.hash(state);
+    }
+}
+
+impl<T> PartialOrd for ArenaId<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for ArenaId<T> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.raw.cmp(&other.raw)
+    }
+}
+
+impl<T> Key for ArenaId<T> {
+    #[inline]
+    fn from_raw(raw: RawId) -> Self {
+        Self {
+            raw,
+            _type: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn into_raw(self) -> RawId {
+        self.raw
+    }
+}
+
+impl<T> From<RawId> for ArenaId<T> {
+    #[inline]
+    fn from(raw: RawId) -> Self {
+        Self::from_raw(raw)
+    }
+}
+
+impl<T> From<ArenaId<T>> for RawId {
+    #[inline]
+    fn from(id: ArenaId<T>) -> Self {
+        id.raw
+    }
+}
+
+/// Declares new key types that can be used with [`Arena`].
+///
+/// Each declared type is a newtype around a [`RawId`] that implements [`Key`], along
+/// with conversions to and from `RawId`. This is useful when several arenas store
+/// the same value type, but their IDs should not be interchangeable.
+///
+/// # Examples
+///
+/// ```
+/// # use arena::{new_key_type, Arena};
+/// new_key_type! {
+///     /// Identifies a player.
+///     pub struct PlayerId;
+///
+///     /// Identifies an enemy.
+///     pub struct EnemyId;
+/// }
+///
+/// let mut players: Arena<&str, PlayerId> = Arena::with_key();
+/// let mut enemies: Arena<&str, EnemyId> = Arena::with_key();
+///
+/// let foo = players.insert("Foo");
+/// let bar = enemies.insert("Bar");
+///
+/// assert_eq!(players[foo], "Foo");
+/// assert_eq!(enemies[bar], "Bar");
+///
+/// // arenas with custom keys can also be built from existing values
+/// let players: Arena<&str, PlayerId> = Arena::from(["Foo", "Baz"]);
+/// assert_eq!(players.len(), 2);
+/// ```
+#[macro_export]
+macro_rules! new_key_type {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident; $($rest:tt)*) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+        #[repr(transparent)]
+        $vis struct $name($crate::RawId);
+
+        impl $name {
+            /// An ID that will never be assigned to a value.
+            #[allow(dead_code)]
+            pub const NULL: Self = Self($crate::RawId::NULL);
+
+            /// Returns `true` if this is the `NULL` ID.
+            #[allow(dead_code)]
+            #[inline]
+            pub fn is_null(self) -> bool {
+                self.0.is_null()
+            }
+        }
+
+        impl $crate::Key for $name {
+            #[inline]
+            fn from_raw(raw: $crate::RawId) -> Self {
+                Self(raw)
+            }
+
+            #[inline]
+            fn into_raw(self) -> $crate::RawId {
+                self.0
+            }
+        }
+
+        impl ::std::convert::From<$crate::RawId> for $name {
+            #[inline]
+            fn from(raw: $crate::RawId) -> Self {
+                Self(raw)
+            }
+        }
+
+        impl ::std::convert::From<$name> for $crate::RawId {
+            #[inline]
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        $crate::new_key_type!($($rest)*);
+    };
+    () => {};
+}
+
+/// Returns the position of the value assigned with the ID in an arena with the slots
+/// and tag.
+///
+/// Every `Used` slot points at a position less than `values.len()`, so the returned
+/// position is always in bounds, which unsafe code relies on.
+#[inline]
+fn lookup_slots(slots: &[Slot], tag: Tag, id: RawId) -> Result<usize, ArenaError> {
+    #[cfg(feature = "instance-tags")]
+    if id.tag != Tag::NONE && id.tag != tag {
+        return Err(ArenaError::ForeignArena(id));
+    }
+    #[cfg(not(feature = "instance-tags"))]
+    let _ = tag;
+
+    let slot = match slots.get(id.slot()) {
+        Some(slot) => slot,
+        None => return Err(ArenaError::OutOfRange(id)),
+    };
+    match slot.state {
+        State::Used { value } if slot.generation == id.generation => Ok(value),
+        _ => Err(ArenaError::StaleId(id)),
+    }
+}
+
+/// Removes the values that `retain_mut` moved out of the way when it is done or when
+/// its predicate panics, keeping all the values that were not tested yet.
+struct RetainGuard<'a, T, K: Key> {
+    arena: &'a mut Arena<T, K>,
+    read: usize,
+    kept: usize,
+    split: usize,
+}
+
+impl<'a, T, K: Key> Drop for RetainGuard<'a, T, K> {
+    fn drop(&mut self) {
+        let arena = &mut *self.arena;
+
+        // the values in `kept..read` were removed, so shift the untested ones down
+        let removed = self.read - self.kept;
+        if removed > 0 {
+            for i in self.read..arena.values.len() {
+                let slot = arena.slots[i].value_slot;
+                arena.slots[i - removed].value_slot = slot;
+                arena.set_value(slot, i - removed);
+            }
+            arena.values.drain(self.kept..self.read);
+        }
+        arena.partition = self.split + arena.partition.saturating_sub(self.read);
+    }
+}
+
+/// Returns the ID of the value at the position in an arena with the slots and tag.
+#[inline]
+fn position_id<K: Key>(slots: &[Slot], tag: Tag, index: usize) -> K {
+    let idx = slots[index].value_slot;
+    K::from_raw(slots[idx].id(idx, tag))
+}
+
+/// Returns the range of positions that the range bounds describe, panicking if it
+/// is not a valid range in a sequence of the length.
+pub(crate) fn check_range<R: RangeBounds<usize>>(range: R, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&i) => i,
+        Bound::Excluded(&i) => i.checked_add(1).expect("range start overflowed"),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&i) => i.checked_add(1).expect("range end overflowed"),
+        Bound::Excluded(&i) => i,
+        Bound::Unbounded => len,
+    };
+    assert!(start <= end, "range starts at {start} but ends at {end}");
+    assert!(
+        end <= len,
+        "range end {end} is out of range for arena of length {len}"
+    );
+    start..end
+}
+
+/// Iterator over an arena's ID/value pairs.
+///
+/// This struct is created by the [`pairs`](Arena::pairs) and
+/// [`pairs_range`](Arena::pairs_range) methods on [`Arena`].
+pub struct Pairs<'a, T, K = ArenaId<T>> {
+    iter: std::iter::Enumerate<std::slice::Iter<'a, T>>,
+    slots: &'a [Slot],
+    offset: usize,
+    tag: Tag,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<'a, T, K: Key> Iterator for Pairs<'a, T, K> {
+    type Item = (K, &'a T);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (idx, val) = self.iter.next()?;
+        Some((position_id(self.slots, self.tag, self.offset + idx), val))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T, K: Key> DoubleEndedIterator for Pairs<'a, T, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (idx, val) = self.iter.next_back()?;
+        Some((position_id(self.slots, self.tag, self.offset + idx), val))
+    }
+}
+
+impl<'a, T, K: Key> ExactSizeIterator for Pairs<'a, T, K> {}
+
+impl<'a, T, K: Key> FusedIterator for Pairs<'a, T, K> {}
+
+impl<'a, T, K> Clone for Pairs<'a, T, K> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            slots: self.slots,
+            offset: self.offset,
+            tag: self.tag,
+            _key: PhantomData,
+        }
+    }
+}
+
+/// Mutable iterator over an arena's ID/value pairs.
+///
+/// This struct is created by the [`pairs_mut`](Arena::pairs_mut) method on [`Arena`].
+pub struct PairsMut<'a, T, K = ArenaId<T>> {
+    iter: std::iter::Enumerate<std::slice::IterMut<'a, T>>,
+    slots: &'a [Slot],
+    offset: usize,
+    tag: Tag,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<'a, T, K: Key> Iterator for PairsMut<'a, T, K> {
+    type Item = (K, &'a mut T);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (idx, val) = self.iter.next()?;
+        Some((position_id(self.slots, self.tag, self.offset + idx), val))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, T, K: Key> DoubleEndedIterator for PairsMut<'a, T, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (idx, val) = self.iter.next_back()?;
+        Some((position_id(self.slots, self.tag, self.offset + idx), val))
+    }
+}
+
+impl<'a, T, K: Key> ExactSizeIterator for PairsMut<'a, T, K> {}
+
+impl<'a, T, K: Key> FusedIterator for PairsMut<'a, T, K> {}
+
+/// Owning iterator over an arena's ID/value pairs.
+///
+/// This struct is created by the [`into_pairs`](Arena::into_pairs) method on
+/// [`Arena`].
+pub struct IntoPairs<T, K = ArenaId<T>> {
+    iter: std::iter::Enumerate<std::vec::IntoIter<T>>,
+    slots: Vec<Slot>,
+    tag: Tag,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<T, K: Key> Iterator for IntoPairs<T, K> {
+    type Item = (K, T);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (idx, val) = self.iter.next()?;
+        Some((position_id(&self.slots, self.tag, idx), val))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<T, K: Key> DoubleEndedIterator for IntoPairs<T, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (idx, val) = self.iter.next_back()?;
+        Some((position_id(&self.slots, self.tag, idx), val))
+    }
+}
+
+impl<T, K: Key> ExactSizeIterator for IntoPairs<T, K> {}
+
+impl<T, K: Key> FusedIterator for IntoPairs<T, K> {}
+
+impl<T: Clone, K> Clone for IntoPairs<T, K> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            iter: self.iter.clone(),
+            slots: self.slots.clone(),
+            tag: self.tag,
+            _key: PhantomData,
+        }
+    }
+}
+
+/// Iterator over an arena's IDs.
+///
+/// This struct is created by the [`ids`](Arena::ids) method on [`Arena`].
+pub struct Ids<'a, K> {
+    positions: Range<usize>,
+    slots: &'a [Slot],
+    tag: Tag,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<'a, K: Key> Iterator for Ids<'a, K> {
+    type Item = K;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.positions.next()?;
+        Some(position_id(self.slots, self.tag, index))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.positions.size_hint()
+    }
+}
+
+impl<'a, K: Key> DoubleEndedIterator for Ids<'a, K> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let index = self.positions.next_back()?;
+        Some(position_id(self.slots, self.tag, index))
+    }
+}
+
+impl<'a, K: Key> ExactSizeIterator for Ids<'a, K> {}
+
+impl<'a, K: Key> FusedIterator for Ids<'a, K> {}
+
+impl<'a, K> Clone for Ids<'a, K> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            positions: self.positions.clone(),
+            slots: self.slots,
+            tag: self.tag,
+            _key: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod ser {
+    use crate::{Key, Slot, State, MAX_SLOTS};
+    use serde::de::Error;
+    use serde::ser::SerializeStruct;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::num::NonZeroU32;
+
+    impl<T: Serialize, K: Key> Serialize for crate::Arena<T, K> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut s = serializer.serialize_struct("Arena", 3)?;
+            s.serialize_field("generation", &self.base_generation)?;
+
+            // retired slots are stored with a generation of zero
+            let slots: Vec<u32> = self
+                .slots
+                .iter()
+                .map(|slot| match slot.state {
+                    State::Retired => 0,
+                    _ => slot.generation.get(),
+                })
+                .collect();
+            s.serialize_field("slots", &slots)?;
+
+            let entries: Vec<Entry<'_, T>> = self
+                .values
+                .iter()
+                .enumerate()
+                .map(|(i, val)| Entry {
+                    idx: self.slots[i].value_slot as u32,
+                    val,
+                })
+                .collect();
+            s.serialize_field("entries", &entries)?;
+
+            s.end()
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>, K: Key> Deserialize<'de> for crate::Arena<T, K> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let de: DeArena<T> = DeArena::deserialize(deserializer)?;
+            if de.slots.len() > MAX_SLOTS {
+                return Err(D::Error::custom("arena has too many slots"));
+            }
+
+            let mut slots: Vec<Slot> = de
+                .slots
+                .iter()
+                .map(|&generation| match NonZeroU32::new(generation) {
+                    Some(generation) => Slot {
+                        value_slot: 0,
+                        generation,
+                        state: State::Free { next_free: None },
+                    },
+                    None => Slot {
+                        value_slot: 0,
+                        generation: NonZeroU32::MAX,
+                        state: State::Retired,
+                    },
+                })
+                .collect();
+
+            let mut values = Vec::with_capacity(de.entries.len());
+            for (value, e) in de.entries.into_iter().enumerate() {
+                let idx = e.idx as usize;
+                match slots.get(idx).map(|slot| &slot.state) {
+                    Some(State::Free { .. }) => {}
+                    Some(_) => return Err(D::Error::custom("arena slot is used more than once")),
+                    None => return Err(D::Error::custom("arena entry has no slot")),
+                }
+
+                // every entry has its own slot, so there are enough to store the back-references
+                slots[idx].state = State::Used { value };
+                slots[value].value_slot = idx;
+                values.push(e.val);
+            }
+
+            let mut arena = Self::from_parts(values, slots, de.generation);
+            arena.rebuild_free_list();
+            Ok(arena)
+        }
+    }
+
+    #[derive(Serialize)]
+    struct Entry<'a, T> {
+        idx: u32,
+        val: &'a T,
+    }
+
+    #[derive(Deserialize)]
+    struct DeEntry<T> {
+        idx: u32,
+        val: T,
+    }
+
+    #[derive(Deserialize)]
+    struct DeArena<T> {
+        generation: NonZeroU32,
+        slots: Vec<u32>,
+        entries: Vec<DeEntry<T>>,
+    }
+}
+
+#[test]
+fn rain_test() {
+    let mut arena = Arena::new();
+    let a = arena.insert("a");
+    let b = arena.insert("b");
+    let c = arena.insert("c");
+    let d = arena.insert("d");
+    let e = arena.insert("e");
+
+    arena.remove(b);
+    arena.remove(a);
+
+    let f = arena.insert("f");
+    let g = arena.insert("g");
+
+    assert_eq!(*arena.get(c).unwrap(), "c");
+    assert_eq!(*arena.get(d).unwrap(), "d");
+    assert_eq!(*arena.get(e).unwrap(), "e");
+    assert_eq!(*arena.get(f).unwrap(), "f");
+    assert_eq!(*arena.get(g).unwrap(), "g");
+
+    arena.remove(f);
+
+    assert_eq!(*arena.get(c).unwrap(), "c");
+    assert_eq!(*arena.get(d).unwrap(), "d");
+    assert_eq!(*arena.get(g).unwrap(), "g");
+    assert_eq!(*arena.get(e).unwrap(), "e");
+}
+
+#[test]
+fn rand_test() {
+    let mut arena = Arena::new();
+    let mut map = std::collections::HashMap::new();
+
+    // add A-Z in random order and store their respective IDs
+    let mut chars: Vec<char> = "QWERTYUIOPLKJHGFDSAZXCVBNM".chars().collect();
+    for &chr in &chars {
+        let id = arena.insert(chr);
+        map.insert(chr, id);
+    }
+
+    // sort the characters from A-Z
+    chars.sort();
+
+    // remove the first 10 characters from the alphabet
+    for &chr in chars.iter().take(10) {
+        let id = *map.get(&chr).unwrap();
+        assert_eq!(arena.remove(id), Some(chr));
+    }
+
+    // insert 10 digits
+    for chr in "0123456789".chars() {
+        let id = arena.insert(chr);
+        map.insert(chr, id);
+        chars.push(chr);
+    }
+
+    // make sure every character's ID matches its stored one
+    for chr in chars.iter().skip(10) {
+        let id = *map.get(chr).unwrap();
+        assert_eq!(arena.get(id), Some(chr));
+    }
+}
+
+#[test]
+fn generation_test() {
+    let mut arena = Arena::new();
+    let a = arena.insert('A');
+    arena.remove(a);
+
+    // the freed slot is reused with a new generation
+    let b = arena.insert('B');
+    assert_eq!(arena.slot_count(), 1);
+    assert_ne!(a, b);
+    assert_eq!(arena.get(a), None);
+    assert_eq!(arena.get(b), Some(&'B'));
+
+    // removing the slots does not allow old IDs to match new values
+    arena.clear_all();
+    assert_eq!(arena.slot_count(), 0);
+    let c = arena.insert('C');
+    assert_eq!(arena.get(a), None);
+    assert_eq!(arena.get(b), None);
+    assert_eq!(arena.get(c), Some(&'C'));
+}
+
+#[test]
+fn retire_test() {
+    let mut arena = Arena::new();
+    let a = arena.insert('A');
+    let b = arena.insert('B');
+    arena.remove(a);
+
+    // force the free slot to its last generation
+    arena.slots[a.raw.slot()].generation = NonZeroU32::MAX;
+    let c = arena.insert('C');
+    assert_eq!(c.raw.slot(), a.raw.slot());
+    assert_eq!(arena.get(c), Some(&'C'));
+
+    // removing the value retires the slot instead of freeing it
+    assert_eq!(arena.remove(c), Some('C'));
+    assert_eq!(arena.get(c), None);
+    assert_eq!(arena.free_slot_count(), 0);
+    assert_eq!(arena.retired_slot_count(), 1);
+
+    // new values never use the retired slot
+    let d = arena.insert('D');
+    assert_ne!(d.raw.slot(), c.raw.slot());
+    assert_eq!(arena.get(b), Some(&'B'));
+    assert_eq!(arena.get(c), None);
+    assert_eq!(arena.get(d), Some(&'D'));
+
+    // clearing the arena keeps the retired slot around
+    arena.clear_all();
+    assert_eq!(arena.slot_count(), 1);
+    assert_eq!(arena.retired_slot_count(), 1);
+    let e = arena.insert('E');
+    assert_ne!(e.raw.slot(), c.raw.slot());
+    assert_eq!(arena.get(c), None);
+    assert_eq!(arena.get(e), Some(&'E'));
+}
+
+#[test]
+fn retire_on_clear_test() {
+    let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
+    for slot in &mut arena.slots {
+        slot.generation = NonZeroU32::MAX;
+    }
+    arena.clear();
+    assert_eq!(arena.free_slot_count(), 0);
+    assert_eq!(arena.retired_slot_count(), 3);
+
+    let d = arena.insert('D');
+    assert_eq!(d.raw.slot(), 3);
+    assert_eq!(arena.free_slot_count(), 0);
+}
+
+#[cfg(feature = "instance-tags")]
+#[test]
+#[should_panic(expected = "was used with an arena that did not create it")]
+fn foreign_id_test() {
+    let mut a = Arena::new();
+    let mut b = Arena::new();
+    let id = a.insert('A');
+    b.insert('B');
+    b.get(id);
+}
+
+#[cfg(feature = "instance-tags")]
+#[test]
+fn instance_tag_test() {
+    let mut a = Arena::new();
+    let id = a.insert('A');
+
+    // the null ID is accepted by every arena
+    assert_eq!(a.get(ArenaId::NULL), None);
+
+    // a clone keeps the tag of the original
+    let b = a.clone();
+    assert_eq!(b.get(id), Some(&'A'));
+
+    // IDs of an empty arena are foreign to every other arena
+    let c: Arena<char> = Arena::new();
+    let result = std::panic::catch_unwind(|| c.contains(id));
+    assert!(result.is_err());
+}
+
+#[test]
+fn preserve_order_test() {
+    let mut arena = Arena::new();
+    arena.set_preserve_order(true);
+    let ids: Vec<_> = "ABCDEFGH".chars().map(|c| arena.insert(c)).collect();
+
+    arena.remove(ids[0]);
+    arena.remove(ids[4]);
+    arena.remove_at(2);
+    assert_eq!(arena.pop(), Some('H'));
+    assert_eq!(arena.as_slice(), &['B', 'C', 'F', 'G']);
+
+    for (&id, chr) in ids.iter().zip("ABCDEFGH".chars()) {
+        match arena.get(id) {
+            Some(&val) => assert_eq!(val, chr),
+            None => assert!("ADEH".contains(chr)),
+        }
+    }
+
+    // freed slots are reused as usual
+    let i = arena.insert('I');
+    assert_eq!(arena.slot_count(), 8);
+    assert_eq!(arena.as_slice(), &['B', 'C', 'F', 'G', 'I']);
+    assert_eq!(arena.id_at(4), Some(i));
+
+    // removing through an entry also keeps the order
+    match arena.entry(ids[2]) {
+        Entry::Occupied(entry) => assert_eq!(entry.remove(), 'C'),
+        Entry::Vacant(_) => unreachable!(),
+    }
+    assert_eq!(arena.as_slice(), &['B', 'F', 'G', 'I']);
+    assert_eq!(arena[i], 'I');
+    assert_eq!(arena.get(ids[2]), None);
+}
+
+#[test]
+fn retain_test() {
+    let mut arena = Arena::new();
+    let ids: Vec<_> = (0..100).map(|i| arena.insert(i)).collect();
+
+    // remove some values first so the slots are out of order
+    for &id in ids.iter().step_by(7) {
+        arena.remove(id);
+    }
+    let mut expected: Vec<i32> = arena.as_slice().to_vec();
 
-    let f = arena.insert("f");
-    let g = arena.insert("g");
+    arena.retain(|_, val| val % 3 != 0);
+    expected.retain(|val| val % 3 != 0);
+    assert_eq!(arena.as_slice(), expected.as_slice());
+    assert_eq!(arena.slot_count(), 100);
+    assert_eq!(arena.free_slot_count(), 100 - expected.len());
 
-    assert_eq!(*arena.get(c).unwrap(), "c");
-    assert_eq!(*arena.get(d).unwrap(), "d");
-    assert_eq!(*arena.get(e).unwrap(), "e");
-    assert_eq!(*arena.get(f).unwrap(), "f");
-    assert_eq!(*arena.get(g).unwrap(), "g");
+    for (i, &id) in ids.iter().enumerate() {
+        let i = i as i32;
+        match arena.get(id) {
+            Some(&val) => assert_eq!(val, i),
+            None => assert!(i % 7 == 0 || i % 3 == 0),
+        }
+    }
+    for (i, id) in arena.pairs().map(|(id, _)| id).enumerate() {
+        assert_eq!(arena.index_of(id), Some(i));
+    }
+}
 
-    arena.remove(f);
+#[test]
+fn drain_test() {
+    let mut arena = Arena::new();
+    let ids: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+    arena.remove_stable(ids[3]);
 
-    assert_eq!(*arena.get(c).unwrap(), "c");
-    assert_eq!(*arena.get(d).unwrap(), "d");
-    assert_eq!(*arena.get(g).unwrap(), "g");
-    assert_eq!(*arena.get(e).unwrap(), "e");
+    // dropping the iterator early still removes the whole range
+    let mut drain = arena.drain_range(2..6);
+    assert_eq!(drain.len(), 4);
+    assert_eq!(drain.next(), Some((ids[2], 2)));
+    assert_eq!(drain.next_back(), Some((ids[6], 6)));
+    drop(drain);
+
+    assert_eq!(arena.as_slice(), &[0, 1, 7, 8, 9]);
+    assert_eq!(arena.free_slot_count(), 5);
+    for (i, &id) in ids.iter().enumerate() {
+        assert_eq!(arena.get(id).is_some(), !(2..7).contains(&i));
+    }
+
+    let rest: Vec<_> = arena.drain().collect();
+    assert_eq!(
+        rest,
+        vec![
+            (ids[0], 0),
+            (ids[1], 1),
+            (ids[7], 7),
+            (ids[8], 8),
+            (ids[9], 9)
+        ]
+    );
+    assert!(arena.is_empty());
+    assert_eq!(arena.slot_count(), 10);
+    assert_eq!(arena.free_slot_count(), 10);
+
+    let id = arena.insert(10);
+    assert!(!ids.contains(&id));
+    assert_eq!(arena.get(id), Some(&10));
 }
 
 #[test]
-fn rand_test() {
+fn extract_if_test() {
     let mut arena = Arena::new();
-    let mut map = std::collections::HashMap::new();
+    let ids: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
 
-    // add A-Z in random order and store their respective IDs
-    let mut chars: Vec<char> = "QWERTYUIOPLKJHGFDSAZXCVBNM".chars().collect();
-    for &chr in &chars {
-        let id = arena.insert(chr);
-        map.insert(chr, id);
+    // only the values that were tested get extracted
+    let mut iter = arena.extract_if(|_, val| *val % 2 == 1);
+    assert_eq!(iter.next(), Some((ids[1], 1)));
+    assert_eq!(iter.next(), Some((ids[3], 3)));
+    drop(iter);
+
+    assert_eq!(arena.as_slice(), &[0, 2, 4, 5, 6, 7, 8, 9]);
+    for (i, &id) in ids.iter().enumerate() {
+        match arena.get(id) {
+            Some(&val) => assert_eq!(val, i),
+            None => assert!(i == 1 || i == 3),
+        }
     }
 
-    // sort the characters from A-Z
-    chars.sort();
+    let extracted: Vec<_> = arena.extract_if(|id, _| id == ids[9]).collect();
+    assert_eq!(extracted, vec![(ids[9], 9)]);
+    assert_eq!(arena.as_slice(), &[0, 2, 4, 5, 6, 7, 8]);
+    assert_eq!(arena.free_slot_count(), 3);
+}
 
-    // remove the first 10 characters from the alphabet
-    for &chr in chars.iter().take(10) {
-        let id = *map.get(&chr).unwrap();
-        assert_eq!(arena.remove(id), Some(chr));
+#[test]
+fn sort_test() {
+    // sorted and reverse sorted inputs are common and must not degrade
+    let mut arena: Arena<u32> = (0..200_000).collect();
+    let ids: Vec<_> = arena.ids().collect();
+    arena.sort_by(|a, b| b.cmp(a));
+    assert!(arena.windows(2).all(|w| w[0] > w[1]));
+    arena.sort();
+    assert!(arena.windows(2).all(|w| w[0] < w[1]));
+    for (i, &id) in ids.iter().enumerate() {
+        assert_eq!(arena[id], i as u32);
+        assert_eq!(arena.index_of(id), Some(i));
     }
 
-    // insert 10 digits
-    for chr in "0123456789".chars() {
-        let id = arena.insert(chr);
-        map.insert(chr, id);
-        chars.push(chr);
+    // remove values so the slots no longer line up with the values
+    let mut arena = Arena::new();
+    let ids: Vec<_> = (0..1000u32)
+        .map(|i| arena.insert((i * 7919) % 1000))
+        .collect();
+    for &id in ids.iter().step_by(3) {
+        arena.remove(id);
+    }
+    arena.sort_unstable_by_key(|val| u32::MAX - val);
+    assert!(arena.windows(2).all(|w| w[0] > w[1]));
+    for (i, &id) in ids.iter().enumerate() {
+        if i % 3 != 0 {
+            assert_eq!(arena[id], (i as u32 * 7919) % 1000);
+        }
     }
+    for i in 0..arena.len() {
+        assert_eq!(arena.index_of(arena.id_at(i).unwrap()), Some(i));
+    }
+}
 
-    // make sure every character's ID matches its stored one
-    for chr in chars.iter().skip(10) {
-        let id = *map.get(chr).unwrap();
-        assert_eq!(arena.get(id), Some(chr));
+#[test]
+fn stable_sort_test() {
+    let mut arena = Arena::new();
+    let ids: Vec<_> = (0..100).map(|i| arena.insert((i % 10, i))).collect();
+    arena.sort_by_key(|&(key, _)| key);
+    assert!(arena.windows(2).all(|w| w[0] < w[1]));
+
+    arena.sort_by_cached_key(|&(key, _)| 9 - key);
+    assert!(arena
+        .windows(2)
+        .all(|w| w[0].0 > w[1].0 || (w[0].0 == w[1].0 && w[0].1 < w[1].1)));
+    for (i, &id) in ids.iter().enumerate() {
+        assert_eq!(arena[id], (i % 10, i));
+    }
+}
+
+#[test]
+fn permutation_test() {
+    let mut arena = Arena::new();
+    let ids: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+    arena.remove(ids[1]);
+
+    assert_eq!(arena.as_slice(), &[0, 5, 2, 3, 4]);
+    assert_eq!(
+        arena.apply_permutation(&[0, 1, 2]),
+        Err(PermutationError::WrongLength {
+            expected: 5,
+            found: 3
+        })
+    );
+    assert_eq!(
+        arena.apply_permutation(&[0, 1, 2, 3, 5]),
+        Err(PermutationError::OutOfRange {
+            position: 4,
+            index: 5
+        })
+    );
+    assert_eq!(arena.as_slice(), &[0, 5, 2, 3, 4]);
+
+    arena.apply_permutation(&[4, 3, 2, 1, 0]).unwrap();
+    assert_eq!(arena.as_slice(), &[4, 3, 2, 5, 0]);
+
+    let order = [ids[5], ids[4], ids[3], ids[2], ids[0]];
+    assert_eq!(
+        arena.reorder_by_ids(&[ids[5], ids[4], ids[4], ids[2], ids[0]]),
+        Err(PermutationError::Duplicate {
+            position: 2,
+            index: 0
+        })
+    );
+    arena.reorder_by_ids(&order).unwrap();
+    assert_eq!(arena.as_slice(), &[5, 4, 3, 2, 0]);
+    for (i, &id) in order.iter().enumerate() {
+        assert_eq!(arena.index_of(id), Some(i));
+    }
+}
+
+#[test]
+fn rotate_test() {
+    let mut arena = Arena::new();
+    let ids: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+    arena.remove(ids[3]);
+    arena.remove(ids[6]);
+    let mut expected = arena.as_slice().to_vec();
+
+    for k in 0..=arena.len() {
+        arena.rotate_left(k);
+        expected.rotate_left(k);
+        assert_eq!(arena.as_slice(), expected.as_slice());
+
+        arena.rotate_right(k / 2);
+        expected.rotate_right(k / 2);
+        assert_eq!(arena.as_slice(), expected.as_slice());
+
+        arena.reverse();
+        expected.reverse();
+        assert_eq!(arena.as_slice(), expected.as_slice());
+
+        for (i, &id) in ids.iter().enumerate() {
+            if i != 3 && i != 6 {
+                assert_eq!(arena[id], i);
+            }
+        }
+    }
+}
+
+#[test]
+fn move_test() {
+    let mut arena = Arena::new();
+    let mut expected = Vec::new();
+    let mut ids = Vec::new();
+    for i in 0..8 {
+        let index = (i * 5) % (expected.len() + 1);
+        ids.push(arena.insert_at(index, i));
+        expected.insert(index, i);
+        assert_eq!(arena.as_slice(), expected.as_slice());
+    }
+
+    for (i, &id) in ids.iter().enumerate() {
+        let to = (i * 3) % arena.len();
+        assert!(arena.move_to(id, to));
+        let from = expected.iter().position(|&val| val == i).unwrap();
+        let val = expected.remove(from);
+        expected.insert(to, val);
+        assert_eq!(arena.as_slice(), expected.as_slice());
+    }
+
+    assert!(arena.move_after(ids[0], ids[7]));
+    assert!(arena.move_before(ids[1], ids[0]));
+    assert!(arena.move_to_front(ids[2]));
+    assert!(arena.move_to_back(ids[3]));
+    assert_eq!(arena.as_slice()[0], 2);
+    assert_eq!(arena.as_slice()[7], 3);
+    assert_eq!(
+        arena.index_of(ids[1]).unwrap() + 1,
+        arena.index_of(ids[0]).unwrap()
+    );
+    for (i, &id) in ids.iter().enumerate() {
+        assert_eq!(arena[id], i);
+    }
+
+    arena.remove(ids[4]);
+    assert!(!arena.move_to_front(ids[4]));
+    assert!(!arena.move_before(ids[0], ids[4]));
+}
+
+#[test]
+fn partition_test() {
+    let mut arena = Arena::new();
+    let ids: Vec<_> = (0..20).map(|i| arena.insert(i)).collect();
+    let check = |arena: &Arena<i32>, active: &dyn Fn(i32) -> bool| {
+        let split = arena.partition_index();
+        assert!(arena.as_slice()[..split].iter().all(|&val| active(val)));
+        assert!(arena.as_slice()[split..].iter().all(|&val| !active(val)));
+        for (i, &id) in ids.iter().enumerate() {
+            if let Some(&val) = arena.get(id) {
+                assert_eq!(val, i as i32);
+            }
+        }
+    };
+
+    assert_eq!(arena.partition_in_place(|_, val| val % 3 == 0), 7);
+    check(&arena, &|val| val % 3 == 0);
+
+    arena.set_partition(ids[1], true);
+    arena.set_partition(ids[3], false);
+    arena.set_partition(ids[6], true);
+    let active = |val| (val % 3 == 0 && val != 3) || val == 1;
+    check(&arena, &active);
+    assert_eq!(arena.partition_index(), 7);
+
+    arena.remove(ids[0]);
+    arena.remove(ids[2]);
+    arena.remove_stable(ids[9]);
+    arena.remove_stable(ids[4]);
+    check(&arena, &active);
+    assert_eq!(arena.partition_index(), 5);
+
+    arena.insert_at(0, 30);
+    arena.insert_at(arena.partition_index(), 31);
+    arena.insert(32);
+    let active = |val| active(val) || val == 30;
+    check(&arena, &active);
+
+    arena.retain(|_, &val| val != 12 && val != 13);
+    check(&arena, &active);
+    let extracted: Vec<_> = arena.extract_if(|_, val| *val == 15 || *val == 5).collect();
+    assert_eq!(extracted.len(), 2);
+    check(&arena, &active);
+    assert_eq!(arena.partition_index(), 4);
+
+    arena.drain_range(2..6);
+    assert_eq!(arena.partition_index(), 2);
+    while arena.pop().is_some() {
+        assert!(arena.partition_index() <= arena.len());
+    }
+}
+
+#[test]
+fn bucketed_test() {
+    const BUCKETS: usize = 5;
+
+    let mut arena = BucketedArena::new(BUCKETS);
+    let mut ids = Vec::new();
+    let mut buckets = Vec::new();
+    let check = |arena: &BucketedArena<usize>, ids: &[ArenaId<usize>], buckets: &[usize]| {
+        for (i, (&id, &bucket)) in ids.iter().zip(buckets).enumerate() {
+            if bucket == usize::MAX {
+                assert_eq!(arena.get(id), None);
+            } else {
+                assert_eq!(arena[id], i);
+                assert_eq!(arena.bucket_of(id), Some(bucket));
+                assert!(arena.bucket_slice(bucket).contains(&i));
+            }
+        }
+        let total: usize = (0..BUCKETS).map(|b| arena.bucket_slice(b).len()).sum();
+        assert_eq!(total, arena.len());
+    };
+
+    for i in 0..50 {
+        let bucket = (i * 7) % BUCKETS;
+        ids.push(arena.insert(bucket, i));
+        buckets.push(bucket);
+    }
+    check(&arena, &ids, &buckets);
+
+    for i in 0..50 {
+        let bucket = (i * 3 + 1) % BUCKETS;
+        assert!(arena.set_bucket(ids[i], bucket));
+        buckets[i] = bucket;
+        check(&arena, &ids, &buckets);
+    }
+
+    for i in (0..50).step_by(3) {
+        assert_eq!(arena.remove(ids[i]), Some(i));
+        buckets[i] = usize::MAX;
+        check(&arena, &ids, &buckets);
+    }
+    assert!(!arena.set_bucket(ids[0], 1));
+}
+
+#[test]
+fn dedup_test() {
+    let values = [5, 3, 3, 1, 5, 5, 2, 3, 1, 4, 4];
+
+    let mut arena: Arena<_> = Arena::from(values);
+    let ids: Vec<_> = (0..values.len()).map(|i| arena.id_at(i).unwrap()).collect();
+    let remap = arena.dedup();
+    assert_eq!(arena.as_slice(), &[5, 3, 1, 5, 2, 3, 1, 4]);
+    assert_eq!(remap.len(), 3);
+    for (i, &id) in ids.iter().enumerate() {
+        assert_eq!(arena[remap.resolve(id)], values[i]);
+        assert_eq!(arena.contains(id), !remap.contains(id));
+    }
+
+    let mut arena: Arena<_> = Arena::from(values);
+    let ids: Vec<_> = (0..values.len()).map(|i| arena.id_at(i).unwrap()).collect();
+    let remap = arena.dedup_by_hash();
+    assert_eq!(arena.as_slice(), &[5, 3, 1, 2, 4]);
+    assert_eq!(remap.len(), 6);
+    for (i, &id) in ids.iter().enumerate() {
+        let first = values.iter().position(|&val| val == values[i]).unwrap();
+        assert_eq!(remap.resolve(id), ids[first]);
+    }
+
+    let mut arena: Arena<_> = Arena::from(values);
+    let ids: Vec<_> = (0..values.len()).map(|i| arena.id_at(i).unwrap()).collect();
+    let remap = arena.sort_dedup();
+    assert_eq!(arena.as_slice(), &[1, 2, 3, 4, 5]);
+    assert_eq!(remap.len(), 6);
+    for (i, &id) in ids.iter().enumerate() {
+        let first = values.iter().position(|&val| val == values[i]).unwrap();
+        assert_eq!(remap.resolve(id), ids[first]);
+    }
+}
+
+#[test]
+fn compact_test() {
+    let mut arena = Arena::new();
+    let mut ids: Vec<_> = (0..1000).map(|i| arena.insert(i)).collect();
+    for i in (0..1000).filter(|i| i % 7 != 0) {
+        arena.remove(ids[i]);
+    }
+    let len = arena.len();
+    let values = arena.as_slice().to_vec();
+
+    arena.trim_free_tail();
+    assert_eq!(arena.slot_count(), 995);
+
+    let stale = ids.clone();
+    let remap = arena.compact_slots();
+    assert_eq!(arena.slot_count(), len);
+    assert_eq!(arena.free_slot_count(), 0);
+    assert_eq!(arena.as_slice(), values.as_slice());
+    for id in &mut ids {
+        *id = remap.resolve(*id);
     }
+    for (i, &id) in ids.iter().enumerate() {
+        if i % 7 == 0 {
+            assert_eq!(arena[id], i);
+        } else {
+            assert_eq!(arena.get(id), None);
+        }
+    }
+
+    // new values never match the IDs from before the compaction
+    for i in 0..1000 {
+        arena.insert(i);
+    }
+    for (&old, &new) in stale.iter().zip(&ids) {
+        if old != new {
+            assert_eq!(arena.get(old), None);
+        }
+    }
+}
+
+#[test]
+fn growth_test() {
+    let mut arena = Arena::new();
+    arena.set_growth_policy(GrowthPolicy::Chunk(100));
+    let ids: Vec<_> = (0..250).map(|i| arena.insert(i)).collect();
+    assert_eq!(arena.capacity(), 300);
+    assert_eq!(arena.slot_capacity(), 300);
+
+    // fallible inserts follow the same policy
+    let mut fallible = Arena::new();
+    fallible.set_growth_policy(GrowthPolicy::Chunk(100));
+    for i in 0..300 {
+        fallible.try_insert(i).unwrap();
+    }
+    assert_eq!(fallible.capacity(), 300);
+    assert_eq!(fallible.slot_capacity(), 300);
+
+    // free slots are reused before the slots grow again
+    for &id in &ids[..60] {
+        arena.remove(id);
+    }
+    for i in 0..100 {
+        arena.insert(i);
+    }
+    assert_eq!(arena.capacity(), 300);
+    assert_eq!(arena.slot_capacity(), 300);
+
+    arena.clear();
+    arena.shrink_to(10);
+    assert_eq!(arena.capacity(), 10);
+    assert_eq!(arena.slot_capacity(), 290);
+    arena.trim_free_tail();
+    arena.shrink_to_fit();
+    assert_eq!(arena.slot_capacity(), 0);
+
+    arena.set_growth_policy(GrowthPolicy::Double);
+    arena.reserve_exact(50);
+    assert_eq!(arena.capacity(), 50);
+    assert_eq!(arena.slot_capacity(), 50);
+}
+
+#[test]
+fn try_insert_test() {
+    let mut arena = Arena::new();
+    let a = arena.insert(1);
+    let b = arena.insert(2);
+    arena.remove(a);
+
+    let failed = arena.try_insert_with(Err::<i32, _>).unwrap_err();
+    assert_eq!(arena.len(), 1);
+    assert_eq!(arena.free_slot_count(), 1);
+    assert_eq!(arena.next_id(), failed);
+
+    let c = arena.try_insert_with(|_| Ok::<_, ()>(3)).unwrap();
+    assert_eq!(c, failed);
+    assert_eq!(arena.get(a), None);
+    assert_eq!(arena[b], 2);
+    assert_eq!(arena[c], 3);
+
+    let d = arena.try_insert(4).unwrap();
+    assert_eq!(arena[d], 4);
+    assert!(arena.try_reserve(usize::MAX).is_err());
+    assert_eq!(arena.len(), 3);
+}
+
+#[test]
+fn panic_safety_test() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    fn check(arena: &Arena<i32>, ids: &[ArenaId<i32>]) {
+        assert_eq!(arena.pairs().count(), arena.len());
+        for (id, &val) in arena.pairs() {
+            assert_eq!(arena[id], val);
+            assert_eq!(ids[val as usize], id);
+        }
+        assert!(arena.partition_index() <= arena.len());
+    }
+
+    let mut arena = Arena::new();
+    let ids: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+    arena.remove(ids[3]);
+    arena.partition_in_place(|_, &val| val < 5);
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        arena.insert_with(|_| panic!("create"));
+    }));
+    assert!(result.is_err());
+    assert_eq!(arena.len(), 9);
+    assert_eq!(arena.free_slot_count(), 1);
+    check(&arena, &ids);
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let mut count = 0;
+        arena.sort_by(|a, b| {
+            count += 1;
+            if count == 10 {
+                panic!("compare");
+            }
+            b.cmp(a)
+        });
+    }));
+    assert!(result.is_err());
+    check(&arena, &ids);
+
+    let before = arena.as_slice().to_vec();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        arena.retain(|_, &val| {
+            if val == 7 {
+                panic!("retain");
+            }
+            val % 2 == 0
+        });
+    }));
+    assert!(result.is_err());
+    let seven = before.iter().position(|&val| val == 7).unwrap();
+    let expected: Vec<i32> = before[..seven]
+        .iter()
+        .copied()
+        .filter(|val| val % 2 == 0)
+        .chain(before[seven..].iter().copied())
+        .collect();
+    assert_eq!(arena.as_slice(), expected.as_slice());
+    check(&arena, &ids);
+
+    let before = arena.as_slice().to_vec();
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        let extracted: Vec<_> = arena
+            .extract_if(|_, val| {
+                if *val == 2 {
+                    panic!("extract");
+                }
+                *val == 9
+            })
+            .collect();
+        drop(extracted);
+    }));
+    assert!(result.is_err());
+    let two = before.iter().position(|&val| val == 2).unwrap();
+    let expected: Vec<i32> = before[..two]
+        .iter()
+        .copied()
+        .filter(|&val| val != 9)
+        .chain(before[two..].iter().copied())
+        .collect();
+    assert_eq!(arena.as_slice(), expected.as_slice());
+    check(&arena, &ids);
+}
+
+#[test]
+fn error_test() {
+    let mut arena = Arena::new();
+    let a = arena.insert('A');
+    let b = arena.insert('B');
+    let c = arena.insert('C');
+    arena.remove(c);
+    let null = ArenaId::NULL;
+
+    assert_eq!(arena.try_get(c), Err(ArenaError::StaleId(c.into())));
+    assert_eq!(
+        arena.try_get(null),
+        Err(ArenaError::OutOfRange(null.into()))
+    );
+    assert_eq!(
+        arena.try_remove(null),
+        Err(ArenaError::OutOfRange(null.into()))
+    );
+    assert_eq!(arena.remove(null), None);
+    assert_eq!(
+        arena.try_get2_mut(a, c).unwrap_err(),
+        ArenaError::StaleId(c.into())
+    );
+    assert_eq!(
+        arena.try_get2_mut(b, b).unwrap_err(),
+        ArenaError::AliasedIds(b.into())
+    );
+    assert_eq!(
+        arena.try_swap(3, 0),
+        Err(ArenaError::IndexOutOfBounds { index: 3, len: 2 })
+    );
+
+    let err = ArenaError::StaleId(c.into());
+    assert_eq!(
+        err.to_string(),
+        format!("{:?} refers to a removed value", RawId::from(c))
+    );
+}
+
+#[test]
+#[should_panic(expected = "refers to a removed value")]
+fn index_panic_test() {
+    let mut arena = Arena::new();
+    let a = arena.insert('A');
+    arena.remove(a);
+    let _ = arena[a];
+}
+
+#[test]
+fn disjoint_test() {
+    let mut arena = Arena::new();
+    let ids: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
+
+    // every ordering of every combination of IDs, including repeats
+    for a in 0..4 {
+        for b in 0..4 {
+            #[allow(deprecated)]
+            if a != b {
+                let (val_a, val_b) = arena.get2_mut(ids[a], ids[b]);
+                assert_eq!((*val_a.unwrap(), *val_b.unwrap()), (a, b));
+            }
+
+            match arena.try_get2_mut(ids[a], ids[b]) {
+                Ok((val_a, val_b)) => assert_eq!((*val_a, *val_b), (a, b)),
+                Err(err) => {
+                    assert_eq!(a, b);
+                    assert_eq!(err, ArenaError::AliasedIds(ids[b].into()));
+                }
+            }
+
+            for c in 0..4 {
+                let expected = if a == b || a == c {
+                    Err(ArenaError::AliasedIds(
+                        ids[if a == b { b } else { c }].into(),
+                    ))
+                } else if b == c {
+                    Err(ArenaError::AliasedIds(ids[c].into()))
+                } else {
+                    Ok(vec![a, b, c])
+                };
+
+                let result = arena.get_disjoint_mut([ids[a], ids[b], ids[c]]);
+                let result = result.map(|vals| vals.iter().map(|val| **val).collect::<Vec<_>>());
+                assert_eq!(result, expected);
+
+                let result = arena.get_many_mut(&[ids[a], ids[b], ids[c]]);
+                let result = result.map(|vals| vals.iter().map(|val| **val).collect::<Vec<_>>());
+                assert_eq!(result, expected);
+            }
+        }
+    }
+
+    // the sorted check for larger amounts of IDs
+    let mut arena: Arena<usize> = (0..20).collect();
+    let mut ids: Vec<_> = (0..20).rev().map(|i| arena.id_at(i).unwrap()).collect();
+    let vals = arena.get_many_mut(&ids).unwrap();
+    assert!(vals.into_iter().map(|val| *val).eq((0..20).rev()));
+    ids[15] = ids[3];
+    ids[17] = ids[3];
+    assert_eq!(
+        arena.get_many_mut(&ids).unwrap_err(),
+        ArenaError::AliasedIds(ids[15].into())
+    );
+
+    arena.remove(ids[0]);
+    assert_eq!(
+        arena.get_disjoint_mut([ids[1], ids[0]]).unwrap_err(),
+        ArenaError::StaleId(ids[0].into())
+    );
+}
+
+#[test]
+fn split_test() {
+    let mut arena: Arena<i32> = (0..10).collect();
+    let ids: Vec<_> = (0..10).map(|i| arena.id_at(i).unwrap()).collect();
+    arena.remove(ids[7]);
+
+    for (i, &id) in ids.iter().enumerate() {
+        let Some((val, mut rest)) = arena.split_at_id_mut(id) else {
+            assert_eq!(i, 7);
+            continue;
+        };
+        assert_eq!(*val, i as i32);
+        assert_eq!(rest.len(), 8);
+        assert_eq!(rest.get(id), None);
+        assert_eq!(rest.get(ids[7]), None);
+
+        *val = rest.iter().sum();
+        for (other, val) in rest.pairs_mut() {
+            assert_ne!(other, id);
+            *val += 100;
+        }
+        for (j, &other) in ids.iter().enumerate() {
+            if j != i && j != 7 {
+                assert!(*rest.get_mut(other).unwrap() >= 100);
+            }
+        }
+        for val in rest.iter_mut() {
+            *val -= 100;
+        }
+    }
+    assert_eq!(arena.pairs().count(), 9);
+    for (id, _) in arena.pairs() {
+        assert!(ids.contains(&id));
+    }
+}
+
+#[test]
+fn branded_test() {
+    let mut arena: Arena<i32> = (0..10).collect();
+    let ids: Vec<_> = (0..10).map(|i| arena.id_at(i).unwrap()).collect();
+    arena.remove(ids[3]);
+    arena.swap(0, 8);
+
+    for &id in &ids {
+        if let Some(&val) = arena.get(id) {
+            assert_eq!(unsafe { *arena.get_unchecked(id) }, val);
+            unsafe { *arena.get_unchecked_mut(id) += 1 };
+            assert_eq!(arena[id], val + 1);
+        }
+    }
+
+    let total = arena.scope(|mut scope| {
+        assert_eq!(scope.len(), 9);
+        assert!(scope.brand(ids[3]).is_none());
+
+        let branded: Vec<_> = ids.iter().filter_map(|&id| scope.brand(id)).collect();
+        assert_eq!(branded.len(), 9);
+
+        for &id in &branded {
+            assert_eq!(scope.id(id), arena_id_of(&ids, scope.get(id)));
+            *scope.get_mut(id) *= 10;
+        }
+        assert_eq!(scope.as_slice()[branded[0].index()], 10);
+        branded.iter().map(|&id| scope[id]).sum::<i32>()
+    });
+    assert_eq!(total, (1..=10).filter(|&v| v != 4).sum::<i32>() * 10);
+    assert_eq!(arena[ids[9]], 100);
+
+    fn arena_id_of(ids: &[ArenaId<i32>], val: &i32) -> ArenaId<i32> {
+        ids[*val as usize - 1]
+    }
+}
+
+#[test]
+fn cell_test() {
+    let mut arena: Arena<i32> = (0..5).collect();
+    let ids: Vec<_> = (0..5).map(|i| arena.id_at(i).unwrap()).collect();
+    arena.remove(ids[4]);
+
+    let cell = arena.as_cell();
+    assert_eq!(cell.len(), 4);
+    assert!(!cell.contains(ids[4]));
+    assert_eq!(
+        cell.borrow(ids[4]).unwrap_err(),
+        ArenaError::StaleId(ids[4].into())
+    );
+
+    let mut refs: Vec<_> = ids[..4]
+        .iter()
+        .map(|&id| cell.borrow_mut(id).unwrap())
+        .collect();
+    for &id in &ids[..4] {
+        assert_eq!(
+            cell.borrow(id).unwrap_err(),
+            ArenaError::BorrowConflict(id.into())
+        );
+    }
+    for val in &mut refs {
+        **val *= 2;
+    }
+    drop(refs);
+
+    let shared: Vec<_> = ids[..4]
+        .iter()
+        .map(|&id| cell.borrow(id).unwrap())
+        .collect();
+    let again = cell.borrow(ids[0]).unwrap();
+    assert_eq!(*again, 0);
+    assert!(cell.borrow_mut(ids[1]).is_err());
+    drop(shared);
+    assert!(cell.borrow_mut(ids[1]).is_ok());
+    assert!(cell.borrow_mut(ids[0]).is_err());
+    drop(again);
+    *cell.borrow_mut(ids[0]).unwrap() = 10;
+
+    assert_eq!(arena.as_slice(), &[10, 2, 4, 6]);
+}
+
+#[test]
+fn iter_test() {
+    let mut arena: Arena<i32> = (0..6).collect();
+    let ids: Vec<_> = arena.ids().collect();
+    arena.remove(ids[1]);
+    arena.remove(ids[4]);
+    arena.swap(0, 1);
+
+    let live: Vec<_> = arena.pairs().map(|(id, _)| id).collect();
+    assert_eq!(arena.ids().collect::<Vec<_>>(), live);
+    assert_eq!(arena.ids().len(), 4);
+    assert_eq!(
+        arena.ids().rev().collect::<Vec<_>>(),
+        live.iter().rev().copied().collect::<Vec<_>>()
+    );
+    for (id, val) in arena.pairs() {
+        assert_eq!(arena[id], *val);
+    }
+
+    let mut pairs = arena.pairs();
+    assert_eq!(pairs.len(), 4);
+    let (last, _) = pairs.next_back().unwrap();
+    assert_eq!(last, live[3]);
+    assert_eq!(pairs.clone().count(), 3);
+
+    for (id, val) in arena.pairs_mut().rev() {
+        *val += 10 * (live.iter().position(|&l| l == id).unwrap() as i32 + 1);
+    }
+    let range: Vec<_> = arena.pairs_range(1..=2).map(|(id, &v)| (id, v)).collect();
+    assert_eq!(
+        range,
+        vec![(live[1], arena[live[1]]), (live[2], arena[live[2]])]
+    );
+    assert_eq!(arena.pairs_range(..0).next(), None);
+
+    for val in &mut arena {
+        *val -= 1;
+    }
+    let sum: i32 = (&arena).into_iter().sum();
+    assert_eq!(sum, arena.values().sum::<i32>());
+
+    let expected: Vec<_> = arena.pairs().map(|(id, &v)| (id, v)).collect();
+    let owned = arena.into_pairs();
+    assert_eq!(owned.clone().rev().count(), 4);
+    assert_eq!(owned.collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn leak_test() {
+    // after a leaked iterator, only the values before the affected range are kept,
+    // and no ID from before the leak may match any value inserted after it
+    fn check(mut arena: Arena<Vec<u8>>, ids: &[ArenaId<Vec<u8>>], kept: usize) {
+        assert_eq!(arena.len(), kept);
+        for (i, &id) in ids.iter().enumerate() {
+            if i < kept {
+                assert_eq!(arena[id], vec![i as u8]);
+            } else {
+                assert_eq!(arena.index_of(id), None);
+            }
+        }
+
+        let new_ids: Vec<_> = (10..16).map(|i| arena.insert(vec![i])).collect();
+        for &id in &ids[kept..] {
+            assert_eq!(arena.get(id), None);
+            assert!(arena.get_disjoint_mut([id]).is_err());
+            assert_eq!(arena.remove(id), None);
+        }
+        for (i, &id) in new_ids.iter().enumerate() {
+            assert!(!ids.contains(&id));
+            assert!(!new_ids[..i].contains(&id));
+            assert_eq!(arena[id], vec![10 + i as u8]);
+        }
+        assert_eq!(arena.len(), kept + new_ids.len());
+        assert!(arena.partition_index() <= arena.len());
+        for (id, val) in arena.pairs() {
+            assert_eq!(&arena[id], val);
+        }
+    }
+
+    let mut arena: Arena<Vec<u8>> = (0..5).map(|i| vec![i]).collect();
+    let ids: Vec<_> = arena.ids().collect();
+    arena.partition_in_place(|_, _| true);
+    let mut drain = arena.drain_range(1..3);
+    drop(drain.next());
+    std::mem::forget(drain);
+    check(arena, &ids, 1);
+
+    let mut arena: Arena<Vec<u8>> = (0..5).map(|i| vec![i]).collect();
+    let ids: Vec<_> = arena.ids().collect();
+    let mut extract = arena.extract_if(|_, val| val[0] == 1);
+    drop(extract.next());
+    std::mem::forget(extract);
+    check(arena, &ids, 0);
 }
@@ -0,0 +1,128 @@
+use crate::Key;
+use std::collections::hash_map;
+use std::collections::HashMap;
+use std::iter::FusedIterator;
+
+/// A mapping from IDs that were removed from an arena to the IDs that replace them.
+///
+/// This is returned by methods that merge values together, such as
+/// [`dedup`](crate::Arena::dedup), so that any stored IDs can be updated.
+///
+/// # Examples
+///
+/// ```
+/// # use arena::Arena;
+/// let mut arena = Arena::new();
+/// let a = arena.insert('A');
+/// let b = arena.insert('A');
+/// let c = arena.insert('C');
+///
+/// let remap = arena.dedup();
+///
+/// assert_eq!(remap.get(b), Some(a));
+/// assert_eq!(remap.get(c), None);
+///
+/// // `resolve` returns the ID itself if it was not remapped
+/// assert_eq!(remap.resolve(b), a);
+/// assert_eq!(remap.resolve(c), c);
+/// ```
+#[derive(Debug, Clone)]
+pub struct IdRemap<K> {
+    map: HashMap<K, K>,
+}
+
+impl<K: Key> IdRemap<K> {
+    pub(crate) fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, from: K, to: K) {
+        self.map.insert(from, to);
+    }
+
+    /// Returns the ID that replaces the removed ID, or `None` if it was not remapped.
+    #[inline]
+    pub fn get(&self, id: K) -> Option<K> {
+        self.map.get(&id).copied()
+    }
+
+    /// Returns the ID that replaces the removed ID, or the ID itself if it was not
+    /// remapped.
+    #[inline]
+    pub fn resolve(&self, id: K) -> K {
+        self.get(id).unwrap_or(id)
+    }
+
+    /// Returns `true` if the ID was remapped.
+    #[inline]
+    pub fn contains(&self, id: K) -> bool {
+        self.map.contains_key(&id)
+    }
+
+    /// Returns the number of remapped IDs.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if no IDs were remapped.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns an iterator over the pairs of removed IDs and the IDs that replace
+    /// them, in arbitrary order.
+    #[inline]
+    pub fn iter(&self) -> RemapIter<'_, K> {
+        RemapIter {
+            iter: self.map.iter(),
+        }
+    }
+}
+
+impl<K: Key> PartialEq for IdRemap<K> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K: Key> Eq for IdRemap<K> {}
+
+impl<'a, K: Key> IntoIterator for &'a IdRemap<K> {
+    type Item = (K, K);
+    type IntoIter = RemapIter<'a, K>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the remapped IDs of an [`IdRemap`].
+///
+/// This struct is created by the [`iter`](IdRemap::iter) method on [`IdRemap`].
+pub struct RemapIter<'a, K> {
+    iter: hash_map::Iter<'a, K, K>,
+}
+
+impl<'a, K: Key> Iterator for RemapIter<'a, K> {
+    type Item = (K, K);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(&from, &to)| (from, to))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<'a, K: Key> ExactSizeIterator for RemapIter<'a, K> {}
+
+impl<'a, K: Key> FusedIterator for RemapIter<'a, K> {}
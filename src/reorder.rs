@@ -0,0 +1,651 @@
+use crate::{Arena, Key};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+impl<T, K: Key> Arena<T, K> {
+    /// Reorders the values in the arena without invalidating their IDs, so that the
+    /// value currently at position `perm[i]` is moved to position `i`.
+    ///
+    /// This takes *O*(*n*) time. If the slice is not a permutation of the positions
+    /// of the arena's values, an error is returned and the arena is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, PermutationError};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    ///
+    /// arena.apply_permutation(&[2, 0, 1]).unwrap();
+    ///
+    /// assert_eq!(arena.as_slice(), &['C', 'A', 'B']);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[b], 'B');
+    /// assert_eq!(arena[c], 'C');
+    ///
+    /// assert_eq!(
+    ///     arena.apply_permutation(&[0, 1, 1]),
+    ///     Err(PermutationError::Duplicate { position: 2, index: 1 })
+    /// );
+    /// ```
+    pub fn apply_permutation(&mut self, perm: &[usize]) -> Result<(), PermutationError> {
+        self.check_permutation_len(perm.len())?;
+        let mut seen = vec![false; perm.len()];
+        for (position, &index) in perm.iter().enumerate() {
+            match seen.get_mut(index) {
+                None => return Err(PermutationError::OutOfRange { position, index }),
+                Some(true) => return Err(PermutationError::Duplicate { position, index }),
+                Some(seen) => *seen = true,
+            }
+        }
+        self.permute(perm.to_vec());
+        Ok(())
+    }
+
+    /// Reorders the values in the arena without invalidating their IDs, so that they
+    /// are in the same order as the provided IDs.
+    ///
+    /// This takes *O*(*n*) time. Every ID in the arena must appear exactly once, or
+    /// an error is returned and the arena is left unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::{Arena, PermutationError};
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    ///
+    /// arena.reorder_by_ids(&[b, c, a]).unwrap();
+    ///
+    /// assert_eq!(arena.as_slice(), &['B', 'C', 'A']);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[b], 'B');
+    /// assert_eq!(arena[c], 'C');
+    ///
+    /// arena.remove(a);
+    /// assert_eq!(
+    ///     arena.reorder_by_ids(&[a, b]),
+    ///     Err(PermutationError::InvalidId { position: 0 })
+    /// );
+    /// ```
+    pub fn reorder_by_ids(&mut self, ids: &[K]) -> Result<(), PermutationError> {
+        self.check_permutation_len(ids.len())?;
+        let mut seen = vec![false; ids.len()];
+        let mut perm = Vec::with_capacity(ids.len());
+        for (position, &id) in ids.iter().enumerate() {
+            let index = self
+                .index_of(id)
+                .ok_or(PermutationError::InvalidId { position })?;
+            if seen[index] {
+                return Err(PermutationError::Duplicate { position, index });
+            }
+            seen[index] = true;
+            perm.push(index);
+        }
+        self.permute(perm);
+        Ok(())
+    }
+
+    /// Reverses the order of the values in the arena without invalidating their IDs.
+    ///
+    /// This takes *O*(*n*) time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    ///
+    /// arena.reverse();
+    ///
+    /// assert_eq!(arena.as_slice(), &['C', 'B', 'A']);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[b], 'B');
+    /// assert_eq!(arena[c], 'C');
+    /// ```
+    pub fn reverse(&mut self) {
+        let len = self.values.len();
+        self.values.reverse();
+        self.reverse_back_refs(0..len);
+        self.relink(0..len);
+    }
+
+    /// Rotates the values in the arena in-place without invalidating their IDs,
+    /// such that the first `mid` values move to the end while the last
+    /// `len - mid` values move to the front.
+    ///
+    /// This takes *O*(*n*) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid` is greater than the length of the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C', 'D']);
+    /// let a = arena.id_at(0).unwrap();
+    ///
+    /// arena.rotate_left(1);
+    ///
+    /// assert_eq!(arena.as_slice(), &['B', 'C', 'D', 'A']);
+    /// assert_eq!(arena[a], 'A');
+    /// ```
+    pub fn rotate_left(&mut self, mid: usize) {
+        let len = self.values.len();
+        assert!(
+            mid <= len,
+            "rotation {mid} is out of range for arena of length {len}"
+        );
+        self.values.rotate_left(mid);
+
+        // rotate the back-references the same way, by reversing both halves and
+        // then the whole range
+        self.reverse_back_refs(0..mid);
+        self.reverse_back_refs(mid..len);
+        self.reverse_back_refs(0..len);
+        self.relink(0..len);
+    }
+
+    /// Rotates the values in the arena in-place without invalidating their IDs,
+    /// such that the last `k` values move to the front while the first
+    /// `len - k` values move to the end.
+    ///
+    /// This takes *O*(*n*) time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is greater than the length of the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C', 'D']);
+    /// let d = arena.id_at(3).unwrap();
+    ///
+    /// arena.rotate_right(1);
+    ///
+    /// assert_eq!(arena.as_slice(), &['D', 'A', 'B', 'C']);
+    /// assert_eq!(arena[d], 'D');
+    /// ```
+    pub fn rotate_right(&mut self, k: usize) {
+        let len = self.values.len();
+        assert!(
+            k <= len,
+            "rotation {k} is out of range for arena of length {len}"
+        );
+        self.rotate_left(len - k);
+    }
+
+    /// Shuffles the values in the arena without invalidating their IDs, using the
+    /// provided source of randomness.
+    ///
+    /// The function is called with an exclusive upper bound `n`, and must return a
+    /// random number in the range `0..n`. This uses the Fisher-Yates shuffle, which
+    /// takes *O*(*n*) time, and produces a uniform shuffle if the function is uniform.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function returns a number that is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from([1, 2, 3, 4, 5]);
+    /// let ids: Vec<_> = arena.ids().collect();
+    ///
+    /// // a simple linear congruential generator
+    /// let mut state = 42u64;
+    /// arena.shuffle_with(&mut |n| {
+    ///     state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+    ///     ((state >> 33) % n as u64) as usize
+    /// });
+    ///
+    /// let mut values = arena.as_slice().to_vec();
+    /// values.sort();
+    /// assert_eq!(values, vec![1, 2, 3, 4, 5]);
+    ///
+    /// for (i, id) in ids.into_iter().enumerate() {
+    ///     assert_eq!(arena[id], i + 1);
+    /// }
+    /// ```
+    pub fn shuffle_with<R>(&mut self, rng: &mut R)
+    where
+        R: FnMut(usize) -> usize,
+    {
+        for i in (1..self.values.len()).rev() {
+            let j = rng(i + 1);
+            assert!(j <= i, "random index {j} is out of range 0..{}", i + 1);
+            self.swap(i, j);
+        }
+    }
+
+    /// Inserts a value at a position in the arena, shifting all values after it up,
+    /// and returns the ID assigned to the value.
+    ///
+    /// This takes *O*(*n* - `index`) time. The IDs of all other values remain valid.
+    /// If the position is before the [partition point](Self::partition_index), the
+    /// value joins the partition.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let c = arena.insert('C');
+    /// let b = arena.insert_at(1, 'B');
+    ///
+    /// assert_eq!(arena.as_slice(), &['A', 'B', 'C']);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[b], 'B');
+    /// assert_eq!(arena[c], 'C');
+    /// ```
+    pub fn insert_at(&mut self, index: usize, value: T) -> K {
+        let len = self.values.len();
+        assert!(
+            index <= len,
+            "insertion index {index} is out of range for arena of length {len}"
+        );
+        let id = self.insert(value);
+        self.shift_value(len, index);
+        if index < self.partition {
+            self.partition += 1;
+        }
+        id
+    }
+
+    /// Moves the value assigned with the ID to a position in the arena, shifting the
+    /// values in between to fill the gap. Returns `false` if the value is not in the
+    /// arena.
+    ///
+    /// This takes time proportional to the distance the value is moved. All IDs
+    /// remain valid.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than the length of the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    /// let d = arena.insert('D');
+    ///
+    /// assert!(arena.move_to(a, 2));
+    /// assert_eq!(arena.as_slice(), &['B', 'C', 'A', 'D']);
+    ///
+    /// assert!(arena.move_to(d, 1));
+    /// assert_eq!(arena.as_slice(), &['B', 'D', 'C', 'A']);
+    /// assert_eq!(arena[a], 'A');
+    /// assert_eq!(arena[b], 'B');
+    /// assert_eq!(arena[c], 'C');
+    /// assert_eq!(arena[d], 'D');
+    /// ```
+    pub fn move_to(&mut self, id: K, index: usize) -> bool {
+        let len = self.values.len();
+        assert!(
+            index < len,
+            "index {index} is out of range for arena of length {len}"
+        );
+        match self.index_of(id) {
+            Some(from) => {
+                self.shift_value(from, index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the value assigned with the ID to the front of the arena, shifting the
+    /// values before it up. Returns `false` if the value is not in the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
+    /// let c = arena.id_at(2).unwrap();
+    ///
+    /// assert!(arena.move_to_front(c));
+    /// assert_eq!(arena.as_slice(), &['C', 'A', 'B']);
+    /// ```
+    #[inline]
+    pub fn move_to_front(&mut self, id: K) -> bool {
+        match self.index_of(id) {
+            Some(from) => {
+                self.shift_value(from, 0);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the value assigned with the ID to the back of the arena, shifting the
+    /// values after it down. Returns `false` if the value is not in the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from(['A', 'B', 'C']);
+    /// let a = arena.id_at(0).unwrap();
+    ///
+    /// assert!(arena.move_to_back(a));
+    /// assert_eq!(arena.as_slice(), &['B', 'C', 'A']);
+    /// ```
+    #[inline]
+    pub fn move_to_back(&mut self, id: K) -> bool {
+        match self.index_of(id) {
+            Some(from) => {
+                self.shift_value(from, self.values.len() - 1);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the value assigned with the ID so that it is directly before the value
+    /// assigned with `other`. Returns `false` if either value is not in the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    ///
+    /// assert!(arena.move_before(c, a));
+    /// assert_eq!(arena.as_slice(), &['C', 'A', 'B']);
+    ///
+    /// assert!(arena.move_before(c, b));
+    /// assert_eq!(arena.as_slice(), &['A', 'C', 'B']);
+    /// ```
+    pub fn move_before(&mut self, id: K, other: K) -> bool {
+        match (self.index_of(id), self.index_of(other)) {
+            (Some(from), Some(to)) => {
+                self.shift_value(from, if from < to { to - 1 } else { to });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the value assigned with the ID so that it is directly after the value
+    /// assigned with `other`. Returns `false` if either value is not in the arena.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    ///
+    /// assert!(arena.move_after(a, c));
+    /// assert_eq!(arena.as_slice(), &['B', 'C', 'A']);
+    ///
+    /// assert!(arena.move_after(a, b));
+    /// assert_eq!(arena.as_slice(), &['B', 'A', 'C']);
+    /// ```
+    pub fn move_after(&mut self, id: K, other: K) -> bool {
+        match (self.index_of(id), self.index_of(other)) {
+            (Some(from), Some(to)) => {
+                self.shift_value(from, if from <= to { to } else { to + 1 });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the value at position `from` to position `to`, shifting the values in
+    /// between by one to fill the gap.
+    fn shift_value(&mut self, from: usize, to: usize) {
+        let slot = self.slots[from].value_slot;
+        if from < to {
+            self.values[from..=to].rotate_left(1);
+            for i in from..to {
+                self.slots[i].value_slot = self.slots[i + 1].value_slot;
+            }
+            self.slots[to].value_slot = slot;
+            self.relink(from..to + 1);
+        } else if to < from {
+            self.values[to..=from].rotate_right(1);
+            for i in (to..from).rev() {
+                self.slots[i + 1].value_slot = self.slots[i].value_slot;
+            }
+            self.slots[to].value_slot = slot;
+            self.relink(to..from + 1);
+        }
+    }
+
+    /// Returns the partition point of the arena, which is the number of values at the
+    /// front of the arena that are in the partition.
+    ///
+    /// The partition is set up by [`partition_in_place`](Self::partition_in_place), and
+    /// updated by [`set_partition`](Self::set_partition). Inserted values are added
+    /// after the partition, and removed values leave it, so the values in the partition
+    /// are always `&arena.as_slice()[..arena.partition_index()]`.
+    ///
+    /// The partition point is a position in the arena, so methods that reorder values,
+    /// such as [`swap`](Self::swap) or [`sort`](Self::sort), move values in and out of
+    /// the partition.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena: Arena<_> = Arena::from([1, 2, 3, 4, 5]);
+    /// assert_eq!(arena.partition_index(), 0);
+    ///
+    /// arena.partition_in_place(|_, val| val % 2 == 1);
+    /// assert_eq!(arena.partition_index(), 3);
+    ///
+    /// arena.insert(7);
+    /// assert_eq!(arena.partition_index(), 3);
+    /// ```
+    #[inline]
+    pub fn partition_index(&self) -> usize {
+        self.partition
+    }
+
+    /// Reorders the values in the arena without invalidating their IDs, so that all
+    /// values for which the predicate returns `true` come before all values for which
+    /// it returns `false`, and returns the new partition point.
+    ///
+    /// This takes *O*(*n*) time and calls the predicate once for every value. The
+    /// relative order of the values is not preserved. The partition point is kept up
+    /// to date by the arena, see [`partition_index`](Self::partition_index).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(("a", false));
+    /// let b = arena.insert(("b", true));
+    /// let c = arena.insert(("c", false));
+    /// let d = arena.insert(("d", true));
+    ///
+    /// let split = arena.partition_in_place(|_, (_, active)| *active);
+    /// assert_eq!(split, 2);
+    ///
+    /// for (_, active) in &arena.as_slice()[..split] {
+    ///     assert!(active);
+    /// }
+    /// assert_eq!(arena[a].0, "a");
+    /// assert_eq!(arena[d].0, "d");
+    /// ```
+    pub fn partition_in_place<F>(&mut self, mut pred: F) -> usize
+    where
+        F: FnMut(K, &T) -> bool,
+    {
+        let tag = self.tag.get();
+        let (mut i, mut j) = (0, self.values.len());
+        while i < j {
+            let slot = self.slots[i].value_slot;
+            let id = K::from_raw(self.slots[slot].id(slot, tag));
+            if pred(id, &self.values[i]) {
+                i += 1;
+            } else {
+                j -= 1;
+                self.swap(i, j);
+            }
+        }
+        self.partition = i;
+        i
+    }
+
+    /// Moves the value assigned with the ID in or out of the partition, without
+    /// invalidating any IDs. Returns `false` if the value is not in the arena.
+    ///
+    /// This takes *O*(1) time, by swapping the value with the one at the partition
+    /// boundary and moving the [partition point](Self::partition_index) past it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    ///
+    /// arena.set_partition(c, true);
+    /// arena.set_partition(b, true);
+    /// assert_eq!(arena.partition_index(), 2);
+    /// assert_eq!(arena.as_slice(), &['C', 'B', 'A']);
+    ///
+    /// arena.set_partition(c, false);
+    /// assert_eq!(arena.partition_index(), 1);
+    /// assert_eq!(arena.as_slice(), &['B', 'C', 'A']);
+    /// assert_eq!(arena[a], 'A');
+    /// ```
+    pub fn set_partition(&mut self, id: K, in_partition: bool) -> bool {
+        let index = match self.index_of(id) {
+            Some(index) => index,
+            None => return false,
+        };
+        if in_partition && index >= self.partition {
+            self.swap(index, self.partition);
+            self.partition += 1;
+        } else if !in_partition && index < self.partition {
+            self.partition -= 1;
+            self.swap(index, self.partition);
+        }
+        true
+    }
+
+    /// Reverses the back-references from the value positions in the range to their slots.
+    fn reverse_back_refs(&mut self, range: Range<usize>) {
+        let (mut i, mut j) = (range.start, range.end);
+        while i + 1 < j {
+            j -= 1;
+            let slot = self.slots[i].value_slot;
+            self.slots[i].value_slot = self.slots[j].value_slot;
+            self.slots[j].value_slot = slot;
+            i += 1;
+        }
+    }
+
+    /// Points the slots of the values in the range at their current positions.
+    fn relink(&mut self, range: Range<usize>) {
+        for i in range {
+            self.set_value(self.slots[i].value_slot, i);
+        }
+    }
+
+    fn check_permutation_len(&self, len: usize) -> Result<(), PermutationError> {
+        if len == self.values.len() {
+            Ok(())
+        } else {
+            Err(PermutationError::WrongLength {
+                expected: self.values.len(),
+                found: len,
+            })
+        }
+    }
+}
+
+/// An error returned when reordering an arena with an invalid permutation.
+///
+/// This is returned by the [`apply_permutation`](Arena::apply_permutation) and
+/// [`reorder_by_ids`](Arena::reorder_by_ids) methods on [`Arena`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PermutationError {
+    /// The permutation does not have one entry for every value in the arena.
+    WrongLength {
+        /// The amount of values in the arena.
+        expected: usize,
+        /// The amount of entries in the permutation.
+        found: usize,
+    },
+
+    /// An entry of the permutation is not the position of a value in the arena.
+    OutOfRange {
+        /// The position of the entry in the permutation.
+        position: usize,
+        /// The out of range value position.
+        index: usize,
+    },
+
+    /// The same value appears more than once in the permutation.
+    Duplicate {
+        /// The position of the repeated entry in the permutation.
+        position: usize,
+        /// The position of the repeated value in the arena.
+        index: usize,
+    },
+
+    /// An ID is not assigned to a value in the arena.
+    InvalidId {
+        /// The position of the ID in the order.
+        position: usize,
+    },
+}
+
+impl Display for PermutationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PermutationError::WrongLength { expected, found } => {
+                write!(f, "expected {expected} entries but found {found}")
+            }
+            PermutationError::OutOfRange { position, index } => {
+                write!(
+                    f,
+                    "entry {position} refers to position {index}, which is out of range"
+                )
+            }
+            PermutationError::Duplicate { position, index } => {
+                write!(f, "entry {position} repeats the value at position {index}")
+            }
+            PermutationError::InvalidId { position } => {
+                write!(f, "entry {position} is not an ID in the arena")
+            }
+        }
+    }
+}
+
+impl Error for PermutationError {}
@@ -0,0 +1,177 @@
+use crate::{lookup_slots, Arena, ArenaError, ArenaId, Key, Pairs, PairsMut, Slot, Tag};
+use std::iter::Chain;
+use std::marker::PhantomData;
+use std::slice;
+
+impl<T, K: Key> Arena<T, K> {
+    /// Returns a mutable reference to the value assigned with the ID, along with a view
+    /// of all the other values in the arena, or `None` if the value is not in the arena.
+    ///
+    /// This allows updating one value while reading or updating all the others, without
+    /// looking up the value again for each of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert(1);
+    /// let b = arena.insert(2);
+    /// let c = arena.insert(3);
+    ///
+    /// let (val, rest) = arena.split_at_id_mut(b).unwrap();
+    /// *val += rest.iter().sum::<i32>();
+    ///
+    /// assert_eq!(rest.get(a), Some(&1));
+    /// assert_eq!(rest.get(b), None);
+    /// assert_eq!(arena[b], 6);
+    /// ```
+    pub fn split_at_id_mut(&mut self, id: K) -> Option<(&mut T, RestMut<'_, T, K>)> {
+        let index = self.lookup(id.into_raw())?;
+        let tag = self.tag.get();
+        let (before, rest) = self.values.split_at_mut(index);
+        let (value, after) = rest.split_first_mut()?;
+        let rest = RestMut {
+            before,
+            after,
+            slots: &self.slots,
+            tag,
+            _key: PhantomData,
+        };
+        Some((value, rest))
+    }
+}
+
+/// A view of all the values of an arena except for one.
+///
+/// This struct is created by the [`split_at_id_mut`](Arena::split_at_id_mut) method on
+/// [`Arena`]. Looking up the ID of the excluded value returns `None`.
+pub struct RestMut<'a, T, K = ArenaId<T>> {
+    before: &'a mut [T],
+    after: &'a mut [T],
+    slots: &'a [Slot],
+    tag: Tag,
+    _key: PhantomData<fn() -> K>,
+}
+
+impl<'a, T, K: Key> RestMut<'a, T, K> {
+    /// Returns the number of values in the view.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.before.len() + self.after.len()
+    }
+
+    /// Returns `true` if the view contains no values.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the view contains a value assigned with the ID.
+    #[inline]
+    pub fn contains(&self, id: K) -> bool {
+        self.lookup(id).is_some()
+    }
+
+    /// Returns a reference to the value assigned with the ID, or `None` if the value
+    /// is not in the view.
+    #[inline]
+    pub fn get(&self, id: K) -> Option<&T> {
+        let index = self.lookup(id)?;
+        match index.checked_sub(self.before.len() + 1) {
+            Some(index) => Some(&self.after[index]),
+            None => Some(&self.before[index]),
+        }
+    }
+
+    /// Returns a mutable reference to the value assigned with the ID, or `None` if the
+    /// value is not in the view.
+    #[inline]
+    pub fn get_mut(&mut self, id: K) -> Option<&mut T> {
+        let index = self.lookup(id)?;
+        match index.checked_sub(self.before.len() + 1) {
+            Some(index) => Some(&mut self.after[index]),
+            None => Some(&mut self.before[index]),
+        }
+    }
+
+    /// Returns an iterator over the values in the view.
+    #[inline]
+    pub fn iter(&self) -> Chain<slice::Iter<'_, T>, slice::Iter<'_, T>> {
+        self.before.iter().chain(self.after.iter())
+    }
+
+    /// Returns an iterator that allows modifying each value in the view.
+    #[inline]
+    pub fn iter_mut(&mut self) -> Chain<slice::IterMut<'_, T>, slice::IterMut<'_, T>> {
+        self.before.iter_mut().chain(self.after.iter_mut())
+    }
+
+    /// Returns an iterator over the ID/value pairs in the view.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use arena::Arena;
+    /// let mut arena = Arena::new();
+    /// let a = arena.insert('A');
+    /// let b = arena.insert('B');
+    /// let c = arena.insert('C');
+    ///
+    /// let (_, rest) = arena.split_at_id_mut(b).unwrap();
+    ///
+    /// let mut pairs = rest.pairs();
+    /// assert_eq!(pairs.next(), Some((a, &'A')));
+    /// assert_eq!(pairs.next(), Some((c, &'C')));
+    /// assert_eq!(pairs.next(), None);
+    /// ```
+    pub fn pairs(&self) -> Chain<Pairs<'_, T, K>, Pairs<'_, T, K>> {
+        let before = Pairs {
+            iter: self.before.iter().enumerate(),
+            slots: self.slots,
+            offset: 0,
+            tag: self.tag,
+            _key: PhantomData,
+        };
+        let after = Pairs {
+            iter: self.after.iter().enumerate(),
+            slots: self.slots,
+            offset: self.before.len() + 1,
+            tag: self.tag,
+            _key: PhantomData,
+        };
+        before.chain(after)
+    }
+
+    /// Returns a mutable iterator over the ID/value pairs in the view.
+    pub fn pairs_mut(&mut self) -> Chain<PairsMut<'_, T, K>, PairsMut<'_, T, K>> {
+        let offset = self.before.len() + 1;
+        let before = PairsMut {
+            iter: self.before.iter_mut().enumerate(),
+            slots: self.slots,
+            offset: 0,
+            tag: self.tag,
+            _key: PhantomData,
+        };
+        let after = PairsMut {
+            iter: self.after.iter_mut().enumerate(),
+            slots: self.slots,
+            offset,
+            tag: self.tag,
+            _key: PhantomData,
+        };
+        before.chain(after)
+    }
+
+    /// Returns the position of the value in the arena, or `None` if the value is not
+    /// in the view. Panics if the ID was created by a different arena.
+    #[inline]
+    fn lookup(&self, id: K) -> Option<usize> {
+        match lookup_slots(self.slots, self.tag, id.into_raw()) {
+            Ok(index) if index != self.before.len() => Some(index),
+            Ok(_) => None,
+            Err(err @ ArenaError::ForeignArena(_)) => panic!("{err}"),
+            Err(_) => None,
+        }
+    }
+}